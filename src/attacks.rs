@@ -0,0 +1,170 @@
+use crate::bitboards::{Bitboard, Board, Color, PieceType, Square};
+use crate::movegen::{
+    b_pawn_east_attacks, b_pawn_west_attacks, king_attacks, knight_attacks, w_pawn_east_attacks,
+    w_pawn_west_attacks, LookUp,
+};
+use crate::sliders::{Classical, SliderAttacks};
+
+impl Board {
+    #[inline(always)]
+    pub fn king_square(&self, color: Color) -> Square {
+        self.bb(PieceType::King, color).lsb()
+    }
+
+    // Pieces of both colours attacking sq given the occupancy, so sliders can be
+    // made to see through pieces that are about to move
+    pub fn attackers_to<S: SliderAttacks>(
+        &self,
+        lookup: &LookUp<S>,
+        sq: Square,
+        occupancy: Bitboard,
+    ) -> Bitboard {
+        let idx = sq as usize;
+        let queens = self.pieces[PieceType::Queen as usize];
+        let diagonal = self.pieces[PieceType::Bishop as usize] | queens;
+        let orthogonal = self.pieces[PieceType::Rook as usize] | queens;
+        let attackers = (lookup.pawn_attacks[Color::Black as usize][idx]
+            & self.bb(PieceType::Pawn, Color::White))
+            | (lookup.pawn_attacks[Color::White as usize][idx]
+                & self.bb(PieceType::Pawn, Color::Black))
+            | (lookup.knight_attacks[idx] & self.pieces[PieceType::Knight as usize])
+            | (lookup.king_attacks[idx] & self.pieces[PieceType::King as usize])
+            | (lookup.bishop_moves(sq, occupancy) & diagonal)
+            | (lookup.rook_moves(sq, occupancy) & orthogonal);
+        attackers & occupancy
+    }
+
+    // attackers_to for code without a LookUp, like FEN parsing and make_move. The
+    // leapers are worked out on the spot and the sliders come from `sliders`.
+    pub fn attackers_with<S: SliderAttacks>(
+        &self,
+        sliders: &S,
+        sq: Square,
+        occupancy: Bitboard,
+    ) -> Bitboard {
+        let bb = sq.bitboard();
+        let queens = self.pieces[PieceType::Queen as usize];
+        let diagonal = self.pieces[PieceType::Bishop as usize] | queens;
+        let orthogonal = self.pieces[PieceType::Rook as usize] | queens;
+        // A white pawn attacks sq from where a black pawn on sq would attack
+        let attackers = ((b_pawn_east_attacks(bb) | b_pawn_west_attacks(bb))
+            & self.bb(PieceType::Pawn, Color::White))
+            | ((w_pawn_east_attacks(bb) | w_pawn_west_attacks(bb))
+                & self.bb(PieceType::Pawn, Color::Black))
+            | (knight_attacks(bb) & self.pieces[PieceType::Knight as usize])
+            | (king_attacks(bb) & self.pieces[PieceType::King as usize])
+            | (sliders.bishop_moves(sq, occupancy) & diagonal)
+            | (sliders.rook_moves(sq, occupancy) & orthogonal);
+        attackers & occupancy
+    }
+
+    // Whether the side that just moved left its own king in check, which no legal
+    // game can reach. Needs no LookUp, so FEN parsing and validate can ask.
+    pub fn opponent_in_check(&self) -> bool {
+        let king = self.king_square(self.active_color.opposite());
+        !(self.attackers_with(&Classical, king, self.occupancy())
+            & self.colors[self.active_color as usize])
+            .is_empty()
+    }
+
+    // The attackers_to that belong to `by`
+    #[inline(always)]
+    pub fn attackers_by<S: SliderAttacks>(
+        &self,
+        lookup: &LookUp<S>,
+        sq: Square,
+        occupancy: Bitboard,
+        by: Color,
+    ) -> Bitboard {
+        self.attackers_to(lookup, sq, occupancy) & self.colors[by as usize]
+    }
+
+    pub fn is_square_attacked<S: SliderAttacks>(
+        &self,
+        lookup: &LookUp<S>,
+        sq: Square,
+        by: Color,
+    ) -> bool {
+        !self
+            .attackers_by(lookup, sq, self.occupancy(), by)
+            .is_empty()
+    }
+
+    // Enemy pieces giving check to the side to move
+    pub fn checkers<S: SliderAttacks>(&self, lookup: &LookUp<S>) -> Bitboard {
+        let us = self.active_color;
+        self.attackers_by(
+            lookup,
+            self.king_square(us),
+            self.occupancy(),
+            us.opposite(),
+        )
+    }
+
+    pub fn in_check<S: SliderAttacks>(&self, lookup: &LookUp<S>) -> bool {
+        !self.checkers(lookup).is_empty()
+    }
+
+    // Pieces of either colour that are all that stands between color's king and an
+    // enemy slider. Ours are pinned, theirs can move off with a discovered check.
+    pub fn blockers_for_king<S: SliderAttacks>(
+        &self,
+        lookup: &LookUp<S>,
+        color: Color,
+    ) -> Bitboard {
+        let king = self.king_square(color);
+        let them = color.opposite();
+        let occupancy = self.occupancy();
+        let queens = self.bb(PieceType::Queen, them);
+        let snipers = (lookup.rook_moves(king, Bitboard::EMPTY)
+            & (self.bb(PieceType::Rook, them) | queens))
+            | (lookup.bishop_moves(king, Bitboard::EMPTY)
+                & (self.bb(PieceType::Bishop, them) | queens));
+
+        let mut blockers = Bitboard::EMPTY;
+        for sniper in snipers {
+            let between = lookup.between[king as usize][sniper as usize] & occupancy;
+            if between.popcount() == 1 {
+                blockers |= between;
+            }
+        }
+        blockers
+    }
+
+    // Pieces of color that can't leave the line to their king without exposing it
+    pub fn pinned<S: SliderAttacks>(&self, lookup: &LookUp<S>, color: Color) -> Bitboard {
+        self.blockers_for_king(lookup, color) & self.colors[color as usize]
+    }
+
+    // Whether the pawn on from can take en passant on ep without leaving its king
+    // attacked. Removing two pawns from one rank is more than pins and check masks
+    // can see, so replay the capture on the occupancy and look at what reaches the
+    // king. Move generation passes its own backend, make_move a table-free one.
+    pub fn en_passant_is_legal<S: SliderAttacks>(
+        &self,
+        sliders: &S,
+        from: Square,
+        ep: Square,
+    ) -> bool {
+        let us = self.active_color;
+        let victim = Square::from_index(ep as usize ^ 8);
+        let occupancy = (self.occupancy() ^ from.bitboard() ^ victim.bitboard()) | ep.bitboard();
+        (self.attackers_with(sliders, self.king_square(us), occupancy)
+            & self.colors[us.opposite() as usize])
+            .is_empty()
+    }
+
+    // The en passant square, but only when the side to move has a legal capture onto
+    // it. Otherwise the position is the same as one without it, and keys and so
+    // repetitions must see it that way. Needs no LookUp so make_move can use it.
+    pub fn en_passant_target(&self) -> Option<Square> {
+        let ep = self.en_passant?;
+        let victim = Square::from_index(ep as usize ^ 8);
+        let capturers = (victim.bitboard().east() | victim.bitboard().west())
+            & self.bb(PieceType::Pawn, self.active_color);
+        capturers
+            .into_iter()
+            .any(|from| self.en_passant_is_legal(&Classical, from, ep))
+            .then_some(ep)
+    }
+}
@@ -0,0 +1,249 @@
+use std::fmt::Write;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::Instant;
+
+use rand::SeedableRng;
+use rand_xoshiro::Xoshiro256PlusPlus;
+
+use abdo_chess::bitboards::{Bitboard, Square};
+use abdo_chess::movegen::{
+    bishop_attacks, bishop_mask, find_magic, rook_attacks, rook_mask, transform, BISHOP_BITS,
+    ROOK_BITS,
+};
+
+const USAGE: &str = "usage: magicgen [--seed <n>] [--threads <n>] [--shrink <bits>] \
+                     [--attempts <n>] [--out <file>]";
+
+// Searches rook and bishop magics and writes them as Rust source for magics.rs to
+// include, e.g.
+//   cargo run --release --bin magicgen -- --shrink 1 --out src/magic_numbers.rs
+struct Options {
+    // Every square gets its own stream, jumped ahead from this seed, so the result
+    // doesn't depend on the thread count
+    seed: u64,
+    threads: usize,
+    // How many bits below ROOK_BITS/BISHOP_BITS to try for
+    shrink: u64,
+    // Per square and bit count
+    attempts: u64,
+    out: Option<String>,
+}
+
+impl Default for Options {
+    fn default() -> Options {
+        Options {
+            seed: 0,
+            threads: std::thread::available_parallelism().map_or(1, |n| n.get()),
+            shrink: 1,
+            attempts: 10_000_000,
+            out: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Found {
+    magic: u64,
+    bits: u64,
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let result = parse_options(&args).and_then(|options| run(&options));
+    if let Err(err) = result {
+        eprintln!("{}", err);
+        std::process::exit(1);
+    }
+}
+
+fn parse_options(args: &[String]) -> Result<Options, String> {
+    let mut options = Options::default();
+    let mut args = args.iter();
+    while let Some(flag) = args.next() {
+        let value = args.next().ok_or(USAGE.to_string())?;
+        let number = || {
+            value
+                .parse::<u64>()
+                .map_err(|_| format!("invalid value for {}: {}", flag, value))
+        };
+        match flag.as_str() {
+            "--seed" => options.seed = number()?,
+            "--threads" => options.threads = number()?.max(1) as usize,
+            "--shrink" => options.shrink = number()?,
+            "--attempts" => options.attempts = number()?,
+            "--out" => options.out = Some(value.clone()),
+            _ => return Err(USAGE.to_string()),
+        }
+    }
+    Ok(options)
+}
+
+// Jobs 0..64 are the bishop squares, 64..128 the rook squares
+fn job(index: usize) -> (Square, bool) {
+    (Square::from_index(index % 64), index < 64)
+}
+
+fn run(options: &Options) -> Result<(), String> {
+    let start = Instant::now();
+    let next = AtomicUsize::new(0);
+    let finished = AtomicUsize::new(0);
+    let results: Mutex<Vec<Option<Found>>> = Mutex::new(vec![None; 128]);
+
+    std::thread::scope(|scope| {
+        for _ in 0..options.threads {
+            scope.spawn(|| loop {
+                let index = next.fetch_add(1, Ordering::Relaxed);
+                if index >= 128 {
+                    break;
+                }
+                let found = search(options, index);
+                results.lock().unwrap()[index] = found;
+                let done = finished.fetch_add(1, Ordering::Relaxed) + 1;
+                let (square, bishop) = job(index);
+                let piece = if bishop { "bishop" } else { "rook" };
+                match found {
+                    Some(found) => eprintln!(
+                        "[{:>3}/128] {:<6} {} {:>2} bits 0x{:016x}",
+                        done, piece, square, found.bits, found.magic
+                    ),
+                    None => eprintln!("[{:>3}/128] {:<6} {} no magic found", done, piece, square),
+                }
+            });
+        }
+    });
+
+    let results = results.into_inner().unwrap();
+    let mut bishops = [None; 64];
+    let mut rooks = [None; 64];
+    for (index, found) in results.into_iter().enumerate() {
+        let (square, bishop) = job(index);
+        let found = found.ok_or(format!(
+            "no magic for the {} on {} within {} attempts, try more",
+            if bishop { "bishop" } else { "rook" },
+            square,
+            options.attempts
+        ))?;
+        if !verify(square, bishop, found) {
+            return Err(format!("magic 0x{:016x} for {} fails", found.magic, square));
+        }
+        if bishop {
+            bishops[square as usize] = Some(found);
+        } else {
+            rooks[square as usize] = Some(found);
+        }
+    }
+    let bishops = bishops.map(Option::unwrap);
+    let rooks = rooks.map(Option::unwrap);
+
+    let source = render(options, &bishops, &rooks);
+    match &options.out {
+        Some(path) => std::fs::write(path, source).map_err(|e| format!("{}: {}", path, e))?,
+        None => print!("{}", source),
+    }
+    eprintln!(
+        "{} bishop and {} rook slots, {} bits saved, in {:.1} s",
+        table_size(&bishops),
+        table_size(&rooks),
+        bits_saved(&bishops, &BISHOP_BITS) + bits_saved(&rooks, &ROOK_BITS),
+        start.elapsed().as_secs_f64()
+    );
+    Ok(())
+}
+
+// The usual bit count first, then one fewer at a time until a search fails
+fn search(options: &Options, index: usize) -> Option<Found> {
+    let (square, bishop) = job(index);
+    let mut rng = Xoshiro256PlusPlus::seed_from_u64(options.seed);
+    for _ in 0..index {
+        rng.jump();
+    }
+    let default_bits = if bishop {
+        BISHOP_BITS[square as usize]
+    } else {
+        ROOK_BITS[square as usize]
+    };
+
+    let mut best = None;
+    for bits in (default_bits.saturating_sub(options.shrink).max(1)..=default_bits).rev() {
+        match find_magic(square, bits, bishop, &mut rng, options.attempts) {
+            Some(magic) => best = Some(Found { magic, bits }),
+            None => break,
+        }
+    }
+    best
+}
+
+// Checked again from scratch against the ray walk, independently of the search
+fn verify(square: Square, bishop: bool, found: Found) -> bool {
+    let mask = if bishop {
+        bishop_mask(square)
+    } else {
+        rook_mask(square)
+    };
+    let mut slots = vec![None; 1 << found.bits];
+    let mut occupancy = Bitboard::EMPTY;
+    loop {
+        let attacks = if bishop {
+            bishop_attacks(square, occupancy)
+        } else {
+            rook_attacks(square, occupancy)
+        };
+        let slot = &mut slots[transform(occupancy, found.magic, found.bits) as usize];
+        match *slot {
+            None => *slot = Some(attacks),
+            Some(other) if other != attacks => return false,
+            Some(_) => {}
+        }
+        occupancy = Bitboard(occupancy.0.wrapping_sub(mask.0) & mask.0);
+        if occupancy.is_empty() {
+            return true;
+        }
+    }
+}
+
+fn table_size(found: &[Found; 64]) -> usize {
+    found.iter().map(|found| 1 << found.bits).sum()
+}
+
+fn bits_saved(found: &[Found; 64], default_bits: &[u64; 64]) -> u64 {
+    found
+        .iter()
+        .zip(default_bits)
+        .map(|(found, bits)| bits - found.bits)
+        .sum()
+}
+
+fn render(options: &Options, bishops: &[Found; 64], rooks: &[Found; 64]) -> String {
+    let mut out = String::new();
+    writeln!(
+        out,
+        "// Generated by magicgen --seed {} --shrink {} --attempts {}, don't edit by hand.",
+        options.seed, options.shrink, options.attempts
+    )
+    .unwrap();
+    writeln!(
+        out,
+        "// {} bishop and {} rook slots.",
+        table_size(bishops),
+        table_size(rooks)
+    )
+    .unwrap();
+    for (name, found) in [("BISHOP", bishops), ("ROOK", rooks)] {
+        writeln!(out, "\n#[rustfmt::skip]").unwrap();
+        writeln!(out, "pub const {}_MAGICS: [u64; 64] = [", name).unwrap();
+        for found in found {
+            writeln!(out, "    0x{:016x},", found.magic).unwrap();
+        }
+        writeln!(out, "];").unwrap();
+
+        writeln!(out, "\n#[rustfmt::skip]").unwrap();
+        writeln!(out, "pub const {}_MAGIC_BITS: [u64; 64] = [", name).unwrap();
+        for rank in found.chunks(8) {
+            let bits: Vec<String> = rank.iter().map(|found| found.bits.to_string()).collect();
+            writeln!(out, "  {},", bits.join(", ")).unwrap();
+        }
+        writeln!(out, "];").unwrap();
+    }
+    out
+}
@@ -1,53 +1,46 @@
+#[derive(Debug, Clone)]
 pub struct Board {
     pub squares: [Option<Piece>; 64],
-    pub pieces: [u64; 6],
-    pub colors: [u64; 2],
+    pub pieces: [Bitboard; 6],
+    pub colors: [Bitboard; 2],
     pub active_color: Color,
     pub castling_rights: CastlingRights,
+    // Rook file for each castling right, indexed like the CastlingRights bits
+    pub castling_rook_files: [u8; 4],
+    // UCI_Chess960: castling is written king-takes-rook and FEN uses Shredder letters.
+    // Only notation depends on it, see set_chess960.
+    pub(crate) chess960: bool,
     pub en_passant: Option<Square>,
     pub halfmove_clock: u16,
     pub fullmove_clock: u16,
+    pub key: u64,
 }
 
-pub fn print_bitboard(bitboard: &u64) {
-    for rank in (0..8).rev() {
-        for file in 0..8 {
-            let index = rank * 8 + file;
-            let bit = (bitboard >> index) & 1;
-            print!("{} ", bit);
-        }
-        println!();
-    }
-}
-
-impl std::fmt::Display for Board {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        for rank in (0..8).rev() {
-            for file in 0..8 {
-                let idx = rank * 8 + file;
-                match &self.squares[idx] {
-                    Some(piece) => {
-                        let symbol = match piece.r#type {
-                            PieceType::Pawn => 'P',
-                            PieceType::Bishop => 'B',
-                            PieceType::Knight => 'N',
-                            PieceType::Rook => 'R',
-                            PieceType::Queen => 'Q',
-                            PieceType::King => 'K',
-                        };
-                        let symbol = if piece.color == Color::Black {
-                            symbol.to_ascii_lowercase()
-                        } else {
-                            symbol
-                        };
-                        write!(f, "{} ", symbol)?;
-                    }
-                    None => write!(f, ". ")?,
+// Boards are equal when they hold the same position. The rook file of a right that
+// is already gone is not part of it, and neither is the Chess960 mode: two boards
+// that differ only there are equal even though to_fen writes their castling
+// rights differently.
+impl PartialEq for Board {
+    fn eq(&self, other: &Board) -> bool {
+        let rook_files = |board: &Board| {
+            let mut files = [None; 4];
+            for (idx, file) in files.iter_mut().enumerate() {
+                if board.castling_rights.bits() & (1 << idx) != 0 {
+                    *file = Some(board.castling_rook_files[idx]);
                 }
             }
-            writeln!(f)?;
-        }
-        Ok(())
+            files
+        };
+        self.squares == other.squares
+            && self.pieces == other.pieces
+            && self.colors == other.colors
+            && self.active_color == other.active_color
+            && self.castling_rights == other.castling_rights
+            && rook_files(self) == rook_files(other)
+            && self.en_passant == other.en_passant
+            && self.halfmove_clock == other.halfmove_clock
+            && self.fullmove_clock == other.fullmove_clock
+            && self.key == other.key
     }
 }
 
@@ -59,119 +52,47 @@ impl Default for Board {
 }
 
 impl Board {
-    fn empty() -> Board {
+    pub(crate) fn empty() -> Board {
         Board {
             squares: [None; 64],
-            pieces: [0; 6],
-            colors: [0; 2],
+            pieces: [Bitboard::EMPTY; 6],
+            colors: [Bitboard::EMPTY; 2],
             active_color: Color::White,
             castling_rights: CastlingRights::NONE,
+            castling_rook_files: [7, 0, 7, 0],
+            chess960: false,
             en_passant: None,
             halfmove_clock: 0,
             fullmove_clock: 1,
+            key: 0,
         }
     }
-    fn from_fen(fen: &str) -> Result<Board, String> {
-        let mut board = Board::empty();
 
-        let part: Vec<&str> = fen.split(' ').collect();
-        if part.len() != 6 {
-            return Err("ERROR: Invalid FEN String".to_string());
-        }
-
-        board.parse_pieces(part[0]);
-        board.parse_active_color(part[1]);
-        board.parse_castling_availabilty(part[2]);
-        board.parse_enpassant(part[3]);
-        board.parse_halfmove_clock(part[4]);
-        board.parse_fullmove_clock(part[5]);
-
-        Ok(board)
-    }
-
-    fn parse_pieces(&mut self, pieces: &str) {
-        // nbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR
-        let piece_row: Vec<&str> = pieces.splitn(8, '/').collect();
-        for (rank, row) in piece_row.iter().enumerate() {
-            let mut file = 0;
-            for piece in row.chars() {
-                if piece.is_ascii_digit() {
-                    file += piece.to_digit(10).unwrap();
-                    continue;
-                }
-                let color = if piece.is_uppercase() {
-                    Color::White
-                } else {
-                    Color::Black
-                };
-                let piece_type = match piece.to_ascii_lowercase() {
-                    'p' => PieceType::Pawn,
-                    'b' => PieceType::Bishop,
-                    'n' => PieceType::Knight,
-                    'r' => PieceType::Rook,
-                    'q' => PieceType::Queen,
-                    'k' => PieceType::King,
-                    _ => continue, // Ignore invalid characters
-                };
-
-                let bit_index: usize = 63 - (rank * 8 + file as usize);
-
-                self.pieces[piece_type as usize] |= 1 << bit_index;
-                self.squares[bit_index] = Some(Piece {
-                    r#type: piece_type,
-                    color,
-                });
-
-                self.colors[color as usize] |= 1 << bit_index;
-                file += 1;
-            }
-        }
+    pub fn chess960(&self) -> bool {
+        self.chess960
     }
 
-    fn parse_active_color(&mut self, active_color: &str) {
-        match active_color {
-            "w" => self.active_color = Color::White,
-            "b" => self.active_color = Color::Black,
-            _ => (),
-        };
+    // Switches castling notation between king-takes-rook moves with Shredder-FEN
+    // letters and the standard e1g1 and KQkq. from_fen turns it on by itself for
+    // Shredder letters or kings and rooks off their usual files, this is for a
+    // Chess960 game that starts from a position that looks standard.
+    pub fn set_chess960(&mut self, chess960: bool) {
+        self.chess960 = chess960;
     }
 
-    fn parse_castling_availabilty(&mut self, castling_availablity: &str) {
-        if castling_availablity == "KQkq" {
-            self.castling_rights = CastlingRights::ALL;
-            return;
-        } else if castling_availablity.is_empty() {
-            self.castling_rights = CastlingRights::NONE;
-            return;
-        }
-        for ch in castling_availablity.chars() {
-            match ch {
-                'K' => self.castling_rights |= CastlingRights::WHITEKINGSIDE,
-                'Q' => self.castling_rights |= CastlingRights::WHITEQUEENSIDE,
-                'k' => self.castling_rights |= CastlingRights::BLACKKINGSIDE,
-                'q' => self.castling_rights |= CastlingRights::BLACKQUEENSIDE,
-                _ => (),
-            }
-        }
-    }
-    fn parse_enpassant(&mut self, en_passant: &str) {
-        self.en_passant = Square::from_str(en_passant);
-    }
-    fn parse_halfmove_clock(&mut self, halfmove_clock: &str) {
-        self.halfmove_clock = halfmove_clock.parse().unwrap();
-    }
-    fn parse_fullmove_clock(&mut self, fullmove_clock: &str) {
-        self.fullmove_clock = fullmove_clock.parse().unwrap();
+    #[inline(always)]
+    pub fn occupancy(&self) -> Bitboard {
+        self.colors[0] | self.colors[1]
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Piece {
     pub r#type: PieceType,
     pub color: Color,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum PieceType {
     Pawn,
     Bishop,
@@ -181,13 +102,33 @@ pub enum PieceType {
     King,
 }
 
-#[derive(Debug, PartialEq, Clone, Copy)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum Color {
     White,
     Black,
 }
 
+impl Color {
+    pub fn opposite(self) -> Color {
+        match self {
+            Color::White => Color::Black,
+            Color::Black => Color::White,
+        }
+    }
+}
+
+impl std::fmt::Display for Color {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let name = match self {
+            Color::White => "white",
+            Color::Black => "black",
+        };
+        write!(f, "{}", name)
+    }
+}
+
 bitflags::bitflags! {
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
     pub struct CastlingRights: u8 {
         const NONE = 0;
         const WHITEKINGSIDE = 1 << 0;
@@ -203,7 +144,7 @@ bitflags::bitflags! {
 }
 
 #[rustfmt::skip]
-#[derive(Debug, Clone, Copy, PartialEq, Eq, num_derive::FromPrimitive)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Square {
     A1, B1, C1, D1, E1, F1, G1, H1,
     A2, B2, C2, D2, E2, F2, G2, H2,
@@ -215,26 +156,355 @@ pub enum Square {
     A8, B8, C8, D8, E8, F8, G8, H8,
 }
 
-use num_traits::FromPrimitive;
-
 impl Square {
-    fn from_str(s: &str) -> Option<Square> {
-        if s.len() != 2 {
-            return None;
+    #[rustfmt::skip]
+    pub const ALL: [Square; 64] = {
+        use Square::*;
+        [
+            A1, B1, C1, D1, E1, F1, G1, H1,
+            A2, B2, C2, D2, E2, F2, G2, H2,
+            A3, B3, C3, D3, E3, F3, G3, H3,
+            A4, B4, C4, D4, E4, F4, G4, H4,
+            A5, B5, C5, D5, E5, F5, G5, H5,
+            A6, B6, C6, D6, E6, F6, G6, H6,
+            A7, B7, C7, D7, E7, F7, G7, H7,
+            A8, B8, C8, D8, E8, F8, G8, H8,
+        ]
+    };
+
+    // Panics if index is not below 64
+    #[inline(always)]
+    pub fn from_index(index: usize) -> Square {
+        Square::ALL[index]
+    }
+
+    // file and rank both count from 0, so a1 is (0, 0) and h8 is (7, 7)
+    #[inline(always)]
+    pub fn from_coords(file: u8, rank: u8) -> Option<Square> {
+        if file < 8 && rank < 8 {
+            Some(Square::ALL[(rank * 8 + file) as usize])
+        } else {
+            None
+        }
+    }
+
+    #[inline(always)]
+    pub fn index(self) -> usize {
+        self as usize
+    }
+
+    #[inline(always)]
+    pub fn file(self) -> u8 {
+        self as u8 % 8
+    }
+
+    #[inline(always)]
+    pub fn rank(self) -> u8 {
+        self as u8 / 8
+    }
+
+    #[inline(always)]
+    pub fn bitboard(self) -> Bitboard {
+        Bitboard(1u64 << self as u8)
+    }
+
+    // The square df files and dr ranks away, None if that falls off the board
+    pub fn offset(self, df: i8, dr: i8) -> Option<Square> {
+        let file = self.file() as i8 + df;
+        let rank = self.rank() as i8 + dr;
+        if (0..8).contains(&file) && (0..8).contains(&rank) {
+            Square::from_coords(file as u8, rank as u8)
+        } else {
+            None
         }
+    }
+
+    // Same file, mirrored rank: a1 <-> a8
+    #[inline(always)]
+    pub fn flip_vertical(self) -> Square {
+        Square::ALL[self as usize ^ 56]
+    }
 
-        #[rustfmt::skip]
-        let idx: usize = match s.to_lowercase().as_str() {
-            "a1" => 0, "b1" => 1, "c1" => 2, "d1" => 3, "e1" => 4, "f1" => 5, "g1" => 6, "h1" => 7,
-            "a2" => 8, "b2" => 9, "c2" => 10, "d2" => 11, "e2" => 12, "f2" => 13, "g2" => 14, "h2" => 15,
-            "a3" => 16, "b3" => 17, "c3" => 18, "d3" => 19, "e3" => 20, "f3" => 21, "g3" => 22, "h3" => 23,
-            "a4" => 24, "b4" => 25, "c4" => 26, "d4" => 27, "e4" => 28, "f4" => 29, "g4" => 30, "h4" => 31,
-            "a5" => 32, "b5" => 33, "c5" => 34, "d5" => 35, "e5" => 36, "f5" => 37, "g5" => 38, "h5" => 39,
-            "a6" => 40, "b6" => 41, "c6" => 42, "d6" => 43, "e6" => 44, "f6" => 45, "g6" => 46, "h6" => 47,
-            "a7" => 48, "b7" => 49, "c7" => 50, "d7" => 51, "e7" => 52, "f7" => 53, "g7" => 54, "h7" => 55,
-            "a8" => 56, "b8" => 57, "c8" => 58, "d8" => 59, "e8" => 60, "f8" => 61, "g8" => 62, "h8" => 63,
-            _ => return None,
+    // Same rank, mirrored file: a1 <-> h1
+    #[inline(always)]
+    pub fn flip_horizontal(self) -> Square {
+        Square::ALL[self as usize ^ 7]
+    }
+
+    // King moves between the two squares
+    pub fn distance(self, other: Square) -> u8 {
+        self.file()
+            .abs_diff(other.file())
+            .max(self.rank().abs_diff(other.rank()))
+    }
+
+    // Rook moves on an empty board, counting each rank and file step
+    pub fn manhattan(self, other: Square) -> u8 {
+        self.file().abs_diff(other.file()) + self.rank().abs_diff(other.rank())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseSquareError(pub String);
+
+impl std::fmt::Display for ParseSquareError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "invalid square: {}", self.0)
+    }
+}
+
+impl std::error::Error for ParseSquareError {}
+
+impl std::str::FromStr for Square {
+    type Err = ParseSquareError;
+
+    fn from_str(s: &str) -> Result<Square, ParseSquareError> {
+        let err = || ParseSquareError(s.to_string());
+        let mut chars = s.chars();
+        let (Some(file), Some(rank), None) = (chars.next(), chars.next(), chars.next()) else {
+            return Err(err());
         };
-        FromPrimitive::from_usize(idx)
+        let file = file.to_ascii_lowercase();
+        if !('a'..='h').contains(&file) || !('1'..='8').contains(&rank) {
+            return Err(err());
+        }
+        Square::from_coords(file as u8 - b'a', rank as u8 - b'1').ok_or_else(err)
+    }
+}
+
+impl std::fmt::Display for Square {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "{}{}",
+            (b'a' + self.file()) as char,
+            (b'1' + self.rank()) as char
+        )
+    }
+}
+
+// A set of squares, bit n standing for Square::from_index(n)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Bitboard(pub u64);
+
+impl Bitboard {
+    pub const EMPTY: Bitboard = Bitboard(0);
+    pub const FULL: Bitboard = Bitboard(!0);
+    pub const FILE_A: Bitboard = Bitboard(0x0101010101010101);
+    pub const FILE_H: Bitboard = Bitboard(0x8080808080808080);
+    pub const RANK_1: Bitboard = Bitboard(0x00000000000000ff);
+    pub const RANK_8: Bitboard = Bitboard(0xff00000000000000);
+    pub const LIGHT_SQUARES: Bitboard = Bitboard(0x55aa55aa55aa55aa);
+    pub const DARK_SQUARES: Bitboard = Bitboard(0xaa55aa55aa55aa55);
+
+    // Panics if file is not below 8
+    #[inline(always)]
+    pub fn file(file: u8) -> Bitboard {
+        assert!(file < 8);
+        Bitboard(Bitboard::FILE_A.0 << file)
+    }
+
+    // Panics if rank is not below 8
+    #[inline(always)]
+    pub fn rank(rank: u8) -> Bitboard {
+        assert!(rank < 8);
+        Bitboard(Bitboard::RANK_1.0 << (rank * 8))
+    }
+
+    #[inline(always)]
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    #[inline(always)]
+    pub fn popcount(self) -> u32 {
+        self.0.count_ones()
+    }
+
+    #[inline(always)]
+    pub fn more_than_one(self) -> bool {
+        self.0 & self.0.wrapping_sub(1) != 0
+    }
+
+    #[inline(always)]
+    pub fn contains(self, sq: Square) -> bool {
+        self.0 & (1u64 << sq as u8) != 0
+    }
+
+    // Lowest set square, panics on an empty board
+    #[inline(always)]
+    pub fn lsb(self) -> Square {
+        Square::from_index(self.0.trailing_zeros() as usize)
+    }
+
+    // Highest set square, panics on an empty board
+    #[inline(always)]
+    pub fn msb(self) -> Square {
+        Square::from_index(63 - self.0.leading_zeros() as usize)
+    }
+
+    // Clears the lowest set square and returns it, panics on an empty board
+    #[inline(always)]
+    pub fn pop_lsb(&mut self) -> Square {
+        let sq = self.lsb();
+        self.0 &= self.0 - 1;
+        sq
+    }
+
+    // Every square moved to its flip_vertical
+    #[inline(always)]
+    pub fn flip_vertical(self) -> Bitboard {
+        Bitboard(self.0.swap_bytes())
+    }
+
+    // Every square moved to its flip_horizontal
+    #[inline(always)]
+    pub fn flip_horizontal(self) -> Bitboard {
+        Bitboard(self.0.reverse_bits().swap_bytes())
+    }
+
+    // One step in each direction, dropping whatever falls off the board
+    #[inline(always)]
+    pub fn north(self) -> Bitboard {
+        Bitboard(self.0 << 8)
+    }
+
+    #[inline(always)]
+    pub fn south(self) -> Bitboard {
+        Bitboard(self.0 >> 8)
+    }
+
+    #[inline(always)]
+    pub fn east(self) -> Bitboard {
+        Bitboard((self.0 << 1) & !Bitboard::FILE_A.0)
+    }
+
+    #[inline(always)]
+    pub fn west(self) -> Bitboard {
+        Bitboard((self.0 >> 1) & !Bitboard::FILE_H.0)
+    }
+
+    #[inline(always)]
+    pub fn north_east(self) -> Bitboard {
+        Bitboard((self.0 << 9) & !Bitboard::FILE_A.0)
+    }
+
+    #[inline(always)]
+    pub fn north_west(self) -> Bitboard {
+        Bitboard((self.0 << 7) & !Bitboard::FILE_H.0)
+    }
+
+    #[inline(always)]
+    pub fn south_east(self) -> Bitboard {
+        Bitboard((self.0 >> 7) & !Bitboard::FILE_A.0)
+    }
+
+    #[inline(always)]
+    pub fn south_west(self) -> Bitboard {
+        Bitboard((self.0 >> 9) & !Bitboard::FILE_H.0)
+    }
+}
+
+impl From<Square> for Bitboard {
+    fn from(sq: Square) -> Bitboard {
+        sq.bitboard()
+    }
+}
+
+macro_rules! impl_bit_op {
+    ($op:ident, $method:ident, $assign_op:ident, $assign_method:ident) => {
+        impl std::ops::$op for Bitboard {
+            type Output = Bitboard;
+
+            #[inline(always)]
+            fn $method(self, rhs: Bitboard) -> Bitboard {
+                Bitboard(std::ops::$op::$method(self.0, rhs.0))
+            }
+        }
+
+        impl std::ops::$assign_op for Bitboard {
+            #[inline(always)]
+            fn $assign_method(&mut self, rhs: Bitboard) {
+                std::ops::$assign_op::$assign_method(&mut self.0, rhs.0);
+            }
+        }
+    };
+}
+
+impl_bit_op!(BitAnd, bitand, BitAndAssign, bitand_assign);
+impl_bit_op!(BitOr, bitor, BitOrAssign, bitor_assign);
+impl_bit_op!(BitXor, bitxor, BitXorAssign, bitxor_assign);
+
+impl std::ops::Not for Bitboard {
+    type Output = Bitboard;
+
+    #[inline(always)]
+    fn not(self) -> Bitboard {
+        Bitboard(!self.0)
+    }
+}
+
+impl std::ops::Shl<u32> for Bitboard {
+    type Output = Bitboard;
+
+    #[inline(always)]
+    fn shl(self, rhs: u32) -> Bitboard {
+        Bitboard(self.0 << rhs)
+    }
+}
+
+impl std::ops::Shr<u32> for Bitboard {
+    type Output = Bitboard;
+
+    #[inline(always)]
+    fn shr(self, rhs: u32) -> Bitboard {
+        Bitboard(self.0 >> rhs)
+    }
+}
+
+// Yields the set squares from a1 upwards
+pub struct Squares(Bitboard);
+
+impl Iterator for Squares {
+    type Item = Square;
+
+    #[inline(always)]
+    fn next(&mut self) -> Option<Square> {
+        if self.0.is_empty() {
+            None
+        } else {
+            Some(self.0.pop_lsb())
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.0.popcount() as usize;
+        (len, Some(len))
+    }
+}
+
+impl ExactSizeIterator for Squares {}
+
+impl IntoIterator for Bitboard {
+    type Item = Square;
+    type IntoIter = Squares;
+
+    #[inline(always)]
+    fn into_iter(self) -> Squares {
+        Squares(self)
+    }
+}
+
+impl std::fmt::Display for Bitboard {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        for rank in (0..8).rev() {
+            write!(f, "{} ", rank + 1)?;
+            for file in 0..8 {
+                let sq = Square::from_coords(file, rank).unwrap();
+                write!(f, " {}", if self.contains(sq) { 'x' } else { '.' })?;
+            }
+            writeln!(f)?;
+        }
+        writeln!(f, "   a b c d e f g h")
     }
 }
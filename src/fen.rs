@@ -0,0 +1,770 @@
+use crate::bitboards::{Board, CastlingRights, Color, Piece, PieceType, Square};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FenField {
+    Pieces,
+    ActiveColor,
+    Castling,
+    EnPassant,
+    HalfmoveClock,
+    FullmoveClock,
+}
+
+// `position` is always the byte offset into the FEN string where the problem is
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FenError {
+    FieldCount {
+        position: usize,
+        found: usize,
+    },
+    InvalidChar {
+        field: FenField,
+        position: usize,
+        ch: char,
+    },
+    RankLength {
+        position: usize,
+        files: u32,
+    },
+    RankCount {
+        position: usize,
+        ranks: usize,
+    },
+    MissingKing {
+        position: usize,
+        color: Color,
+    },
+    DuplicateKing {
+        position: usize,
+        color: Color,
+    },
+    PawnOnBackRank {
+        position: usize,
+    },
+    OpponentInCheck {
+        position: usize,
+    },
+    InvalidSquare {
+        field: FenField,
+        position: usize,
+    },
+    EnPassantRank {
+        position: usize,
+        square: Square,
+    },
+    EnPassantPawn {
+        position: usize,
+        square: Square,
+    },
+    DuplicateCastling {
+        position: usize,
+        ch: char,
+    },
+    CastlingPieces {
+        position: usize,
+        ch: char,
+    },
+    InvalidNumber {
+        field: FenField,
+        position: usize,
+    },
+}
+
+impl FenError {
+    pub fn field(&self) -> Option<FenField> {
+        match *self {
+            FenError::FieldCount { .. } => None,
+            FenError::OpponentInCheck { .. } => Some(FenField::ActiveColor),
+            FenError::InvalidChar { field, .. }
+            | FenError::InvalidSquare { field, .. }
+            | FenError::InvalidNumber { field, .. } => Some(field),
+            FenError::RankLength { .. }
+            | FenError::RankCount { .. }
+            | FenError::MissingKing { .. }
+            | FenError::DuplicateKing { .. }
+            | FenError::PawnOnBackRank { .. } => Some(FenField::Pieces),
+            FenError::EnPassantRank { .. } | FenError::EnPassantPawn { .. } => {
+                Some(FenField::EnPassant)
+            }
+            FenError::DuplicateCastling { .. } | FenError::CastlingPieces { .. } => {
+                Some(FenField::Castling)
+            }
+        }
+    }
+
+    pub fn position(&self) -> usize {
+        match *self {
+            FenError::FieldCount { position, .. }
+            | FenError::InvalidChar { position, .. }
+            | FenError::RankLength { position, .. }
+            | FenError::RankCount { position, .. }
+            | FenError::MissingKing { position, .. }
+            | FenError::DuplicateKing { position, .. }
+            | FenError::PawnOnBackRank { position }
+            | FenError::OpponentInCheck { position }
+            | FenError::InvalidSquare { position, .. }
+            | FenError::EnPassantRank { position, .. }
+            | FenError::EnPassantPawn { position, .. }
+            | FenError::DuplicateCastling { position, .. }
+            | FenError::CastlingPieces { position, .. }
+            | FenError::InvalidNumber { position, .. } => position,
+        }
+    }
+}
+
+impl std::fmt::Display for FenField {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let name = match self {
+            FenField::Pieces => "piece placement",
+            FenField::ActiveColor => "active colour",
+            FenField::Castling => "castling",
+            FenField::EnPassant => "en passant",
+            FenField::HalfmoveClock => "halfmove clock",
+            FenField::FullmoveClock => "fullmove number",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl std::fmt::Display for FenError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            FenError::FieldCount { found, .. } => {
+                write!(f, "expected 4 or 6 fields, found {}", found)?
+            }
+            FenError::InvalidChar { ch, .. } => write!(f, "unexpected character '{}'", ch)?,
+            FenError::RankLength { files, .. } => {
+                write!(f, "rank covers {} files instead of 8", files)?
+            }
+            FenError::RankCount { ranks, .. } => write!(f, "found {} ranks instead of 8", ranks)?,
+            FenError::MissingKing { color, .. } => write!(f, "no {} king", color)?,
+            FenError::DuplicateKing { color, .. } => write!(f, "more than one {} king", color)?,
+            FenError::PawnOnBackRank { .. } => write!(f, "pawn on the first or eighth rank")?,
+            FenError::OpponentInCheck { .. } => write!(f, "the side not to move is in check")?,
+            FenError::InvalidSquare { .. } => write!(f, "not a square")?,
+            FenError::EnPassantRank { square, .. } => {
+                write!(f, "{} is not on the en passant rank", square)?
+            }
+            FenError::EnPassantPawn { square, .. } => {
+                write!(f, "no pawn can have just passed over {}", square)?
+            }
+            FenError::DuplicateCastling { ch, .. } => {
+                write!(f, "castling right '{}' repeated", ch)?
+            }
+            FenError::CastlingPieces { ch, .. } => write!(
+                f,
+                "castling right '{}' without king and rook on their home squares",
+                ch
+            )?,
+            FenError::InvalidNumber { .. } => write!(f, "not a valid number")?,
+        }
+        match self.field() {
+            Some(field) => write!(f, " in {} field at character {}", field, self.position()),
+            None => write!(f, " at character {}", self.position()),
+        }
+    }
+}
+
+impl std::error::Error for FenError {}
+
+impl Piece {
+    pub fn from_char(ch: char) -> Option<Piece> {
+        let color = if ch.is_ascii_uppercase() {
+            Color::White
+        } else {
+            Color::Black
+        };
+        let r#type = match ch.to_ascii_lowercase() {
+            'p' => PieceType::Pawn,
+            'b' => PieceType::Bishop,
+            'n' => PieceType::Knight,
+            'r' => PieceType::Rook,
+            'q' => PieceType::Queen,
+            'k' => PieceType::King,
+            _ => return None,
+        };
+        Some(Piece { r#type, color })
+    }
+
+    pub fn to_char(self) -> char {
+        let ch = match self.r#type {
+            PieceType::Pawn => 'p',
+            PieceType::Bishop => 'b',
+            PieceType::Knight => 'n',
+            PieceType::Rook => 'r',
+            PieceType::Queen => 'q',
+            PieceType::King => 'k',
+        };
+        match self.color {
+            Color::White => ch.to_ascii_uppercase(),
+            Color::Black => ch,
+        }
+    }
+}
+
+impl Board {
+    // Accepts the full six fields, or the first four (EPD style) with clocks at 0 and 1
+    pub fn from_fen(fen: &str) -> Result<Board, FenError> {
+        let mut board = Board::empty();
+        board.set_fen(fen)?;
+        Ok(board)
+    }
+
+    // Replaces the position with the one in fen, leaving the board as it was on an
+    // error. A board already in Chess960 mode stays in it even for a FEN that
+    // looks standard.
+    pub fn set_fen(&mut self, fen: &str) -> Result<(), FenError> {
+        let mut board = Board::empty();
+        board.chess960 = self.chess960;
+
+        let part: Vec<(usize, &str)> = fen
+            .split_ascii_whitespace()
+            .map(|part| (part.as_ptr() as usize - fen.as_ptr() as usize, part))
+            .collect();
+        if part.len() != 4 && part.len() != 6 {
+            return Err(FenError::FieldCount {
+                position: fen.len(),
+                found: part.len(),
+            });
+        }
+
+        board.parse_pieces(part[0])?;
+        board.parse_active_color(part[1])?;
+        if board.opponent_in_check() {
+            return Err(FenError::OpponentInCheck {
+                position: part[1].0,
+            });
+        }
+        board.parse_castling_availabilty(part[2])?;
+        board.parse_enpassant(part[3])?;
+        if part.len() == 6 {
+            board.parse_halfmove_clock(part[4])?;
+            board.parse_fullmove_clock(part[5])?;
+        }
+        board.key = board.compute_key();
+
+        *self = board;
+        Ok(())
+    }
+
+    pub fn to_fen(&self) -> String {
+        let mut fen = String::new();
+        for rank in (0..8).rev() {
+            let mut empty = 0;
+            for file in 0..8 {
+                match self.squares[rank * 8 + file] {
+                    Some(piece) => {
+                        if empty > 0 {
+                            fen.push_str(&empty.to_string());
+                            empty = 0;
+                        }
+                        fen.push(piece.to_char());
+                    }
+                    None => empty += 1,
+                }
+            }
+            if empty > 0 {
+                fen.push_str(&empty.to_string());
+            }
+            if rank > 0 {
+                fen.push('/');
+            }
+        }
+
+        fen.push(' ');
+        fen.push(match self.active_color {
+            Color::White => 'w',
+            Color::Black => 'b',
+        });
+
+        fen.push(' ');
+        if self.castling_rights.is_empty() {
+            fen.push('-');
+        }
+        for (idx, ch) in ['K', 'Q', 'k', 'q'].into_iter().enumerate() {
+            if !self
+                .castling_rights
+                .contains(CastlingRights::from_bits_truncate(1 << idx))
+            {
+                continue;
+            }
+            // K and Q name the outermost rook, so any other needs its file even in
+            // standard mode
+            let (color, rank) = if idx < 2 {
+                (Color::White, 0)
+            } else {
+                (Color::Black, 7)
+            };
+            let rook_file = self.castling_rook_files[idx];
+            let mut beyond = if idx % 2 == 0 {
+                rook_file + 1..8
+            } else {
+                0..rook_file
+            };
+            let outermost = !beyond.any(|file| {
+                self.squares[Square::from_coords(file, rank).unwrap().index()]
+                    == Some(Piece {
+                        r#type: PieceType::Rook,
+                        color,
+                    })
+            });
+            if self.chess960 || !outermost {
+                // Shredder-FEN: the rook's file, upper case for White
+                let file = (b'a' + rook_file) as char;
+                fen.push(if idx < 2 {
+                    file.to_ascii_uppercase()
+                } else {
+                    file
+                });
+            } else {
+                fen.push(ch);
+            }
+        }
+
+        fen.push(' ');
+        match self.en_passant {
+            Some(sq) => fen.push_str(&sq.to_string()),
+            None => fen.push('-'),
+        }
+
+        fen.push_str(&format!(" {} {}", self.halfmove_clock, self.fullmove_clock));
+        fen
+    }
+
+    fn parse_pieces(&mut self, (offset, pieces): (usize, &str)) -> Result<(), FenError> {
+        // rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR
+        // Counted up front, so an extra rank isn't reported as whatever is wrong with
+        // the pieces once they land on the wrong ranks
+        let ranks = pieces.split('/').count();
+        if ranks != 8 {
+            let position = match pieces.match_indices('/').nth(7) {
+                Some((idx, _)) => offset + idx,
+                None => offset + pieces.len(),
+            };
+            return Err(FenError::RankCount { position, ranks });
+        }
+
+        let mut rank = 7;
+        let mut file = 0;
+        let mut kings = [None; 2];
+        for (idx, ch) in pieces.char_indices() {
+            let position = offset + idx;
+            if ch == '/' {
+                if file != 8 {
+                    return Err(FenError::RankLength {
+                        position,
+                        files: file,
+                    });
+                }
+                rank -= 1;
+                file = 0;
+                continue;
+            }
+            if let Some(empty) = ch.to_digit(10).filter(|n| (1..=8).contains(n)) {
+                file += empty;
+                if file > 8 {
+                    return Err(FenError::RankLength {
+                        position,
+                        files: file,
+                    });
+                }
+                continue;
+            }
+
+            let piece = Piece::from_char(ch).ok_or(FenError::InvalidChar {
+                field: FenField::Pieces,
+                position,
+                ch,
+            })?;
+            if file == 8 {
+                return Err(FenError::RankLength { position, files: 9 });
+            }
+            if piece.r#type == PieceType::Pawn && (rank == 0 || rank == 7) {
+                return Err(FenError::PawnOnBackRank { position });
+            }
+            if piece.r#type == PieceType::King {
+                if kings[piece.color as usize].is_some() {
+                    return Err(FenError::DuplicateKing {
+                        position,
+                        color: piece.color,
+                    });
+                }
+                kings[piece.color as usize] = Some(position);
+            }
+
+            let bit_index: usize = rank * 8 + file as usize;
+            let sq = Square::from_index(bit_index);
+
+            self.pieces[piece.r#type as usize] |= sq.bitboard();
+            self.squares[bit_index] = Some(piece);
+
+            self.colors[piece.color as usize] |= sq.bitboard();
+            file += 1;
+        }
+
+        let end = offset + pieces.len();
+        if file != 8 {
+            return Err(FenError::RankLength {
+                position: end,
+                files: file,
+            });
+        }
+        for color in [Color::White, Color::Black] {
+            if kings[color as usize].is_none() {
+                return Err(FenError::MissingKing {
+                    position: offset,
+                    color,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    fn parse_active_color(
+        &mut self,
+        (offset, active_color): (usize, &str),
+    ) -> Result<(), FenError> {
+        self.active_color = match active_color {
+            "w" => Color::White,
+            "b" => Color::Black,
+            _ => {
+                let (idx, ch) = active_color
+                    .char_indices()
+                    .find(|&(idx, ch)| idx > 0 || (ch != 'w' && ch != 'b'))
+                    .unwrap();
+                return Err(FenError::InvalidChar {
+                    field: FenField::ActiveColor,
+                    position: offset + idx,
+                    ch,
+                });
+            }
+        };
+        Ok(())
+    }
+
+    // Standard KQkq, Shredder-FEN rook files (HAha) or X-FEN, where K and Q mean the
+    // outermost rook on that side of the king and a file letter picks any other one
+    fn parse_castling_availabilty(
+        &mut self,
+        (offset, castling_availablity): (usize, &str),
+    ) -> Result<(), FenError> {
+        if castling_availablity == "-" {
+            self.castling_rights = CastlingRights::NONE;
+            return Ok(());
+        }
+        for (idx, ch) in castling_availablity.char_indices() {
+            let position = offset + idx;
+            let color = if ch.is_ascii_uppercase() {
+                Color::White
+            } else {
+                Color::Black
+            };
+            let rank = match color {
+                Color::White => 0,
+                Color::Black => 7,
+            };
+            let rook_on = |file: u8| {
+                self.squares[Square::from_coords(file, rank).unwrap().index()]
+                    == Some(Piece {
+                        r#type: PieceType::Rook,
+                        color,
+                    })
+            };
+            let king = (self.pieces[PieceType::King as usize] & self.colors[color as usize]).lsb();
+            if king.rank() != rank {
+                return Err(FenError::CastlingPieces { position, ch });
+            }
+            let king_file = king.file();
+
+            let rook_file = match ch.to_ascii_lowercase() {
+                'k' => (king_file + 1..8).rev().find(|&file| rook_on(file)),
+                'q' => (0..king_file).find(|&file| rook_on(file)),
+                'a'..='h' => {
+                    self.chess960 = true;
+                    Some(ch.to_ascii_lowercase() as u8 - b'a').filter(|&file| rook_on(file))
+                }
+                _ => {
+                    return Err(FenError::InvalidChar {
+                        field: FenField::Castling,
+                        position,
+                        ch,
+                    })
+                }
+            };
+            let rook_file = rook_file
+                .filter(|&file| file != king_file)
+                .ok_or(FenError::CastlingPieces { position, ch })?;
+
+            let right_idx = color as usize * 2 + usize::from(rook_file < king_file);
+            let right = CastlingRights::from_bits_truncate(1 << right_idx);
+            if self.castling_rights.contains(right) {
+                return Err(FenError::DuplicateCastling { position, ch });
+            }
+            if king_file != 4 || (rook_file != 0 && rook_file != 7) {
+                self.chess960 = true;
+            }
+            self.castling_rights |= right;
+            self.castling_rook_files[right_idx] = rook_file;
+        }
+        Ok(())
+    }
+
+    fn parse_enpassant(&mut self, (offset, en_passant): (usize, &str)) -> Result<(), FenError> {
+        if en_passant == "-" {
+            self.en_passant = None;
+            return Ok(());
+        }
+        let square: Square = en_passant.parse().map_err(|_| FenError::InvalidSquare {
+            field: FenField::EnPassant,
+            position: offset,
+        })?;
+        // The square behind a pawn that just moved two, so the side to move captures onto it
+        let rank = match self.active_color {
+            Color::White => 5,
+            Color::Black => 2,
+        };
+        if square.rank() != rank {
+            return Err(FenError::EnPassantRank {
+                position: offset,
+                square,
+            });
+        }
+        if !self.en_passant_is_consistent(square) {
+            return Err(FenError::EnPassantPawn {
+                position: offset,
+                square,
+            });
+        }
+        self.en_passant = Some(square);
+        Ok(())
+    }
+
+    fn parse_halfmove_clock(
+        &mut self,
+        (offset, halfmove_clock): (usize, &str),
+    ) -> Result<(), FenError> {
+        self.halfmove_clock = halfmove_clock
+            .parse()
+            .map_err(|_| FenError::InvalidNumber {
+                field: FenField::HalfmoveClock,
+                position: offset,
+            })?;
+        Ok(())
+    }
+
+    fn parse_fullmove_clock(
+        &mut self,
+        (offset, fullmove_clock): (usize, &str),
+    ) -> Result<(), FenError> {
+        self.fullmove_clock =
+            fullmove_clock
+                .parse()
+                .ok()
+                .filter(|&n| n > 0)
+                .ok_or(FenError::InvalidNumber {
+                    field: FenField::FullmoveClock,
+                    position: offset,
+                })?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // One or more FENs for every FenError, each with the error from_fen must report
+    const CASES: &[(&str, &str, FenError)] = &[
+        (
+            "five fields",
+            "4k3/8/8/8/8/8/8/4K3 w - - 0",
+            FenError::FieldCount {
+                position: 27,
+                found: 5,
+            },
+        ),
+        (
+            "unknown piece",
+            "4k3/8/8/8/8/8/8/4X3 w - - 0 1",
+            FenError::InvalidChar {
+                field: FenField::Pieces,
+                position: 17,
+                ch: 'X',
+            },
+        ),
+        (
+            "unknown active colour",
+            "4k3/8/8/8/8/8/8/4K3 x - - 0 1",
+            FenError::InvalidChar {
+                field: FenField::ActiveColor,
+                position: 20,
+                ch: 'x',
+            },
+        ),
+        (
+            "short last rank",
+            "4k3/8/8/8/8/8/8/4K2 w - - 0 1",
+            FenError::RankLength {
+                position: 19,
+                files: 7,
+            },
+        ),
+        (
+            "long rank",
+            "4k3/8/8/8/8/8/8/4K3p w - - 0 1",
+            FenError::RankLength {
+                position: 19,
+                files: 9,
+            },
+        ),
+        (
+            "nine ranks",
+            "rnbqkbnr/pppppppp/8/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            FenError::RankCount {
+                position: 36,
+                ranks: 9,
+            },
+        ),
+        (
+            "seven ranks",
+            "4k3/8/8/8/8/8/4K3 w - - 0 1",
+            FenError::RankCount {
+                position: 17,
+                ranks: 7,
+            },
+        ),
+        (
+            "no white king",
+            "4k3/8/8/8/8/8/8/8 w - - 0 1",
+            FenError::MissingKing {
+                position: 0,
+                color: Color::White,
+            },
+        ),
+        (
+            "two black kings",
+            "3kk3/8/8/8/8/8/8/4K3 w - - 0 1",
+            FenError::DuplicateKing {
+                position: 2,
+                color: Color::Black,
+            },
+        ),
+        (
+            "pawn on the eighth rank",
+            "4k2P/8/8/8/8/8/8/4K3 w - - 0 1",
+            FenError::PawnOnBackRank { position: 3 },
+        ),
+        (
+            "kings side by side",
+            "8/8/8/8/8/8/8/kK6 w - - 0 1",
+            FenError::OpponentInCheck { position: 18 },
+        ),
+        (
+            "side not to move in check",
+            "4k2R/8/8/8/8/8/8/4K3 w - - 0 1",
+            FenError::OpponentInCheck { position: 21 },
+        ),
+        (
+            "en passant off the board",
+            "4k3/8/8/8/8/8/8/4K3 w - e9 0 1",
+            FenError::InvalidSquare {
+                field: FenField::EnPassant,
+                position: 24,
+            },
+        ),
+        (
+            "en passant behind the mover",
+            "4k3/8/8/8/8/8/8/4K3 w - e3 0 1",
+            FenError::EnPassantRank {
+                position: 24,
+                square: Square::E3,
+            },
+        ),
+        (
+            "en passant without a pawn",
+            "4k3/8/8/8/8/8/8/4K3 w - e6 0 1",
+            FenError::EnPassantPawn {
+                position: 24,
+                square: Square::E6,
+            },
+        ),
+        (
+            "castling right twice",
+            "4k3/8/8/8/8/8/8/4K2R w KK - 0 1",
+            FenError::DuplicateCastling {
+                position: 24,
+                ch: 'K',
+            },
+        ),
+        (
+            "castling without a rook",
+            "4k3/8/8/8/8/8/8/4K3 w K - 0 1",
+            FenError::CastlingPieces {
+                position: 22,
+                ch: 'K',
+            },
+        ),
+        (
+            "halfmove clock not a number",
+            "4k3/8/8/8/8/8/8/4K3 w - - x 1",
+            FenError::InvalidNumber {
+                field: FenField::HalfmoveClock,
+                position: 26,
+            },
+        ),
+        (
+            "halfmove clock too large",
+            "4k3/8/8/8/8/8/8/4K3 w - - 65536 1",
+            FenError::InvalidNumber {
+                field: FenField::HalfmoveClock,
+                position: 26,
+            },
+        ),
+        (
+            "fullmove number zero",
+            "4k3/8/8/8/8/8/8/4K3 w - - 0 0",
+            FenError::InvalidNumber {
+                field: FenField::FullmoveClock,
+                position: 28,
+            },
+        ),
+    ];
+
+    #[test]
+    fn bad_fens() {
+        let mut failures = Vec::new();
+        for (name, fen, expected) in CASES {
+            let result = Board::from_fen(fen);
+            if result.as_ref().err() != Some(expected) {
+                failures.push(format!(
+                    "{}: {:?} (expected {:?})",
+                    name,
+                    result.err(),
+                    expected
+                ));
+            }
+        }
+        assert!(failures.is_empty(), "{}", failures.join("\n"));
+    }
+
+    #[test]
+    fn chess960_mode() {
+        let start = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+        let mut board = Board::from_fen(start).unwrap();
+        assert!(!board.chess960());
+        board.set_chess960(true);
+        assert_eq!(board.to_fen(), start.replace("KQkq", "HAha"));
+        assert_eq!(board, Board::from_fen(start).unwrap());
+
+        board.set_fen(start).unwrap();
+        assert!(board.chess960());
+        assert!(board.set_fen("8/8/8/8/8/8/8/8 w - - 0 1").is_err());
+        assert!(board.chess960());
+
+        // The rook on h1 is further out, so the right for c1 can't be a K
+        let fen = "1k6/8/8/8/8/8/8/1KR4R w C - 0 1";
+        let mut board = Board::from_fen(fen).unwrap();
+        board.set_chess960(false);
+        assert_eq!(board.to_fen(), fen);
+    }
+}
@@ -0,0 +1,115 @@
+use crate::bitboards::Board;
+use crate::fen::FenError;
+use crate::makemove::Undo;
+use crate::movegen::LookUp;
+use crate::moves::Move;
+use crate::outcome::Outcome;
+use crate::sliders::SliderAttacks;
+
+// A board plus the line that led to it. Moves that were popped stay in `moves`
+// past the current ply until redo plays them again or push replaces them.
+#[derive(Debug, Clone)]
+pub struct Game {
+    start_fen: String,
+    board: Board,
+    moves: Vec<Move>,
+    // One per played move: the undo record and the key of the position it was played from
+    undos: Vec<Undo>,
+    keys: Vec<u64>,
+}
+
+impl Default for Game {
+    fn default() -> Game {
+        Game::from_board(Board::default())
+    }
+}
+
+impl Game {
+    pub fn from_fen(fen: &str) -> Result<Game, FenError> {
+        Ok(Game::from_board(Board::from_fen(fen)?))
+    }
+
+    pub fn from_board(board: Board) -> Game {
+        Game {
+            start_fen: board.to_fen(),
+            board,
+            moves: Vec::new(),
+            undos: Vec::new(),
+            keys: Vec::new(),
+        }
+    }
+
+    pub fn start_fen(&self) -> &str {
+        &self.start_fen
+    }
+
+    pub fn board(&self) -> &Board {
+        &self.board
+    }
+
+    // Moves played to reach the current position
+    pub fn moves(&self) -> &[Move] {
+        &self.moves[..self.ply()]
+    }
+
+    // Moves that redo would play, in order
+    pub fn redo_moves(&self) -> &[Move] {
+        &self.moves[self.ply()..]
+    }
+
+    // Keys of every earlier position, oldest first, as Board::outcome wants them
+    pub fn keys(&self) -> &[u64] {
+        &self.keys
+    }
+
+    pub fn ply(&self) -> usize {
+        self.undos.len()
+    }
+
+    // Plays a move that must be legal in the current position, dropping any redo line
+    pub fn push(&mut self, mv: Move) {
+        self.moves.truncate(self.ply());
+        self.moves.push(mv);
+        self.play(mv);
+    }
+
+    // Takes back the last move, keeping it for redo
+    pub fn pop(&mut self) -> Option<Move> {
+        let undo = self.undos.pop()?;
+        self.keys.pop();
+        let mv = self.moves[self.ply()];
+        self.board.unmake_move(mv, &undo);
+        Some(mv)
+    }
+
+    pub fn redo(&mut self) -> Option<Move> {
+        let mv = *self.moves.get(self.ply())?;
+        self.play(mv);
+        Some(mv)
+    }
+
+    // Pops or redoes until `ply` moves have been played. Returns false, leaving the
+    // game as it was, when the line doesn't reach that far.
+    pub fn goto(&mut self, ply: usize) -> bool {
+        if ply > self.moves.len() {
+            return false;
+        }
+        while self.ply() > ply {
+            self.pop();
+        }
+        while self.ply() < ply {
+            self.redo();
+        }
+        true
+    }
+
+    pub fn outcome<S: SliderAttacks>(&self, lookup: &LookUp<S>) -> Option<Outcome> {
+        self.board.outcome(lookup, &self.keys)
+    }
+
+    fn play(&mut self, mv: Move) {
+        self.keys.push(self.board.key);
+        let undo = self.board.make_move(mv);
+        self.undos.push(undo);
+    }
+}
@@ -0,0 +1,20 @@
+pub mod attacks;
+pub mod bitboards;
+pub mod fen;
+pub mod game;
+pub mod magics;
+pub mod makemove;
+pub mod movegen;
+pub mod moves;
+pub mod outcome;
+pub mod perft;
+#[cfg(all(feature = "pext", target_arch = "x86_64"))]
+pub mod pext;
+pub mod render;
+pub mod san;
+pub mod see;
+pub mod sliders;
+pub mod symmetry;
+pub mod uci;
+pub mod validate;
+pub mod zobrist;
@@ -0,0 +1,162 @@
+// Generated by magicgen --seed 0 --shrink 1 --attempts 10000000, don't edit by hand.
+// 5248 bishop and 102400 rook slots.
+
+#[rustfmt::skip]
+pub const BISHOP_MAGICS: [u64; 64] = [
+    0x8040040094010020,
+    0x6008880080820000,
+    0x04082a0404f2004a,
+    0x01a092004200000a,
+    0x302202100221a981,
+    0x0812021005800182,
+    0x4404820110c20001,
+    0x1012010042022000,
+    0x0000892004040061,
+    0x2405020208010910,
+    0x3300112104010000,
+    0x401084440a840010,
+    0x0000040504200810,
+    0x6180091018040c08,
+    0x0410110801110901,
+    0x2240020901011012,
+    0x0020008420022202,
+    0x0010000811010414,
+    0xc808000108410200,
+    0x0014008201220200,
+    0x1384030611200040,
+    0x08060101028a0104,
+    0x0002060406128209,
+    0x020101c044008486,
+    0x02101082086001a0,
+    0x818d280020060401,
+    0x0080240020830401,
+    0x024904002a440080,
+    0x0001001025004022,
+    0x0048002062008408,
+    0x4002042800808800,
+    0x00020c5002004611,
+    0xa0040405a021a000,
+    0x000c010501204403,
+    0x2004802080900482,
+    0x0010e00800010104,
+    0x00400804401a0102,
+    0x001002018000b000,
+    0x2418020410004900,
+    0x00840c00220280a1,
+    0x1020901110040808,
+    0x00022a90042d1803,
+    0x0040802801040801,
+    0x000109c200800808,
+    0x0802401081211200,
+    0x0640310810802908,
+    0x0808080104041050,
+    0x000104010b520200,
+    0x9012011002508406,
+    0x000122050c20a104,
+    0x0013083402080000,
+    0x0004001342020400,
+    0x8004400420820228,
+    0x824408d010a08005,
+    0x4084840488020812,
+    0x0114108405002800,
+    0x8100820082200280,
+    0x11480a0100a84490,
+    0x0130300204524800,
+    0x0008002080460804,
+    0x2108010058210904,
+    0x8400104051aa0082,
+    0x0200302301cc0c20,
+    0x1002080104088200,
+];
+
+#[rustfmt::skip]
+pub const BISHOP_MAGIC_BITS: [u64; 64] = [
+  6, 5, 5, 5, 5, 5, 5, 6,
+  5, 5, 5, 5, 5, 5, 5, 5,
+  5, 5, 7, 7, 7, 7, 5, 5,
+  5, 5, 7, 9, 9, 7, 5, 5,
+  5, 5, 7, 9, 9, 7, 5, 5,
+  5, 5, 7, 7, 7, 7, 5, 5,
+  5, 5, 5, 5, 5, 5, 5, 5,
+  6, 5, 5, 5, 5, 5, 5, 6,
+];
+
+#[rustfmt::skip]
+pub const ROOK_MAGICS: [u64; 64] = [
+    0x8080004002502980,
+    0x0040002000401004,
+    0x8100200040110008,
+    0x0200042010400a01,
+    0x0200020008051020,
+    0x2080018002000400,
+    0x0200208821020004,
+    0x0100008201244700,
+    0x2005800240088022,
+    0x0000400050002000,
+    0x000d0041b0600100,
+    0x0010801000840800,
+    0x0008808008000400,
+    0x021600100600180c,
+    0x2001000100020004,
+    0x0002001102860244,
+    0x0100808000400020,
+    0x011004400640e000,
+    0x0000410010200100,
+    0x0800210008100104,
+    0x0200818004010800,
+    0x0005010008020400,
+    0x3131040010880102,
+    0x9130020001008044,
+    0x0000800300204500,
+    0x4440010100204080,
+    0x4000200080100088,
+    0x0010100080800800,
+    0x0001000500104800,
+    0x0005008900020400,
+    0x0010010400020890,
+    0x4414108200052844,
+    0x1000400082800260,
+    0xc440804000802000,
+    0x0090811001802000,
+    0x2010100081800802,
+    0x0801000801001006,
+    0x1080800200800400,
+    0x3003000401008200,
+    0x000028804200010c,
+    0x0000400080048020,
+    0x08220100208a0041,
+    0x2010002000808010,
+    0x0800080010008080,
+    0x0010080100110004,
+    0x0044000810020200,
+    0x0800890832040050,
+    0x0040004400820001,
+    0x42800882482b0100,
+    0x24e0102040008080,
+    0x0104884200102200,
+    0x0000814804100180,
+    0x0800080010050100,
+    0x1102800200040080,
+    0x001208020130c400,
+    0x400c0461008c0200,
+    0x4000410820800015,
+    0x000c102040010081,
+    0x1820000820c49101,
+    0x0331000821041001,
+    0x0002000804201002,
+    0x8812009004080102,
+    0x020a032218900804,
+    0x8203040101205882,
+];
+
+#[rustfmt::skip]
+pub const ROOK_MAGIC_BITS: [u64; 64] = [
+  12, 11, 11, 11, 11, 11, 11, 12,
+  11, 10, 10, 10, 10, 10, 10, 11,
+  11, 10, 10, 10, 10, 10, 10, 11,
+  11, 10, 10, 10, 10, 10, 10, 11,
+  11, 10, 10, 10, 10, 10, 10, 11,
+  11, 10, 10, 10, 10, 10, 10, 11,
+  11, 10, 10, 10, 10, 10, 10, 11,
+  12, 11, 11, 11, 11, 11, 11, 12,
+];
@@ -0,0 +1,171 @@
+use crate::bitboards::{Bitboard, Square};
+use crate::movegen::{bishop_attacks, bishop_mask, rook_attacks, rook_mask, transform};
+use crate::sliders::SliderAttacks;
+
+// BISHOP_MAGICS, ROOK_MAGICS and the bits each square's magic indexes with, written
+// by the magicgen binary. The tables below are built from them at compile time and
+// a magic that sends two different attack sets to the same slot stops the build.
+include!("magic_numbers.rs");
+
+// Where a square's attacks live in the packed tables: the relevant occupancy is
+// hashed by the magic to one of 1 << (64 - shift) slots starting at offset
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MagicEntry {
+    pub mask: Bitboard,
+    pub magic: u64,
+    pub shift: u32,
+    pub offset: usize,
+}
+
+impl MagicEntry {
+    #[inline(always)]
+    pub const fn index(&self, occupancy: Bitboard) -> usize {
+        self.offset + ((occupancy.0 & self.mask.0).wrapping_mul(self.magic) >> self.shift) as usize
+    }
+}
+
+// Slider attacks through the packed magic tables
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Magic;
+
+impl SliderAttacks for Magic {
+    #[inline(always)]
+    fn bishop_moves(&self, sq: Square, occupancy: Bitboard) -> Bitboard {
+        BISHOP_TABLE[BISHOP_ENTRIES[sq as usize].index(occupancy)]
+    }
+
+    #[inline(always)]
+    fn rook_moves(&self, sq: Square, occupancy: Bitboard) -> Bitboard {
+        ROOK_TABLE[ROOK_ENTRIES[sq as usize].index(occupancy)]
+    }
+}
+
+pub static BISHOP_ENTRIES: [MagicEntry; 64] = build_entries(true);
+pub static ROOK_ENTRIES: [MagicEntry; 64] = build_entries(false);
+
+// One const block per square keeps each evaluation under the compiler's limit.
+// $build::<N>(sq, bishop) fills the square's N = $slots(sq, bishop) entries.
+macro_rules! per_square {
+    ($build:ident, $slots:ident, $bishop:literal) => {
+        per_square!(@ $build, $slots, $bishop;
+            0 1 2 3 4 5 6 7 8 9 10 11 12 13 14 15
+            16 17 18 19 20 21 22 23 24 25 26 27 28 29 30 31
+            32 33 34 35 36 37 38 39 40 41 42 43 44 45 46 47
+            48 49 50 51 52 53 54 55 56 57 58 59 60 61 62 63)
+    };
+    (@ $build:ident, $slots:ident, $bishop:literal; $($sq:literal)*) => {
+        [$(const { &$build::<{ $slots($sq, $bishop) }>($sq, $bishop) } as &[Bitboard]),*]
+    };
+}
+#[cfg(all(feature = "pext", target_arch = "x86_64"))]
+pub(crate) use per_square;
+
+// Every square gets exactly the 1 << bits slots it needs, 41 KB for bishops and
+// 800 KB for rooks with the usual bit counts, instead of padding each square to
+// the largest
+const BISHOP_TABLE_SIZE: usize = table_size(true);
+const ROOK_TABLE_SIZE: usize = table_size(false);
+pub static BISHOP_TABLE: [Bitboard; BISHOP_TABLE_SIZE] =
+    pack(&per_square!(build_square, slots, true));
+pub static ROOK_TABLE: [Bitboard; ROOK_TABLE_SIZE] = pack(&per_square!(build_square, slots, false));
+
+const fn slots(sq: usize, bishop: bool) -> usize {
+    1 << if bishop {
+        BISHOP_MAGIC_BITS[sq]
+    } else {
+        ROOK_MAGIC_BITS[sq]
+    }
+}
+
+const fn table_size(bishop: bool) -> usize {
+    let mut size = 0;
+    let mut sq = 0;
+    while sq < 64 {
+        size += slots(sq, bishop);
+        sq += 1;
+    }
+    size
+}
+
+const fn build_entries(bishop: bool) -> [MagicEntry; 64] {
+    let (magics, bits) = if bishop {
+        (&BISHOP_MAGICS, &BISHOP_MAGIC_BITS)
+    } else {
+        (&ROOK_MAGICS, &ROOK_MAGIC_BITS)
+    };
+    let mut entries = [MagicEntry {
+        mask: Bitboard::EMPTY,
+        magic: 0,
+        shift: 0,
+        offset: 0,
+    }; 64];
+    let mut offset = 0;
+    let mut sq = 0;
+    while sq < 64 {
+        entries[sq] = MagicEntry {
+            mask: if bishop {
+                bishop_mask(Square::ALL[sq])
+            } else {
+                rook_mask(Square::ALL[sq])
+            },
+            magic: magics[sq],
+            shift: 64 - bits[sq] as u32,
+            offset,
+        };
+        offset += slots(sq, bishop);
+        sq += 1;
+    }
+    entries
+}
+
+// The per-square tables back to back, square 0 at offset 0
+pub(crate) const fn pack<const SIZE: usize>(squares: &[&[Bitboard]; 64]) -> [Bitboard; SIZE] {
+    let mut table = [Bitboard::EMPTY; SIZE];
+    let mut offset = 0;
+    let mut sq = 0;
+    while sq < 64 {
+        let mut i = 0;
+        while i < squares[sq].len() {
+            table[offset + i] = squares[sq][i];
+            i += 1;
+        }
+        offset += squares[sq].len();
+        sq += 1;
+    }
+    table
+}
+
+// Attack sets are never empty, so an empty slot is a free one
+const fn build_square<const SIZE: usize>(sq: usize, bishop: bool) -> [Bitboard; SIZE] {
+    let square = Square::ALL[sq];
+    let (mask, magic, bits) = if bishop {
+        (
+            bishop_mask(square),
+            BISHOP_MAGICS[sq],
+            BISHOP_MAGIC_BITS[sq],
+        )
+    } else {
+        (rook_mask(square), ROOK_MAGICS[sq], ROOK_MAGIC_BITS[sq])
+    };
+    let mut table = [Bitboard::EMPTY; SIZE];
+    // Every subset of mask, walked with the carry-rippler trick
+    let mut occupancy = 0u64;
+    loop {
+        let attacks = if bishop {
+            bishop_attacks(square, Bitboard(occupancy))
+        } else {
+            rook_attacks(square, Bitboard(occupancy))
+        };
+        let slot = transform(Bitboard(occupancy), magic, bits) as usize;
+        if table[slot].0 == 0 {
+            table[slot] = attacks;
+        } else if table[slot].0 != attacks.0 {
+            panic!("magic collision, regenerate the magics");
+        }
+        occupancy = occupancy.wrapping_sub(mask.0) & mask.0;
+        if occupancy == 0 {
+            break;
+        }
+    }
+    table
+}
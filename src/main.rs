@@ -1,11 +1,478 @@
-mod bitboards;
-mod movegen;
+use std::collections::HashSet;
+use std::hint::black_box;
+use std::time::Instant;
+
+use abdo_chess::bitboards::{Bitboard, Board, Square};
+use abdo_chess::game::Game;
+use abdo_chess::magics::Magic;
+use abdo_chess::movegen::{bishop_attacks, bishop_mask, rook_attacks, rook_mask, LookUp};
+use abdo_chess::moves::{Move, MoveList};
+use abdo_chess::perft::{divide, perft, SUITE};
+use abdo_chess::sliders::{Classical, Hyperbola, KoggeStone, SliderAttacks};
+
+const USAGE: &str =
+    "usage: abdoChess [perft <depth> [fen] | divide <depth> [fen] | suite [max depth] | bench]";
 
-use bitboards::*;
-use movegen::*;
 fn main() {
-    let board = bitboards::Board::default();
-    println!("{}", board);
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let result = match args.first().map(String::as_str) {
+        None => {
+            print!("{}", Board::default());
+            Ok(())
+        }
+        Some("perft") => run_perft(&args[1..], false),
+        Some("divide") => run_perft(&args[1..], true),
+        Some("suite") => run_suite(&args[1..]),
+        Some("bench") => run_bench(),
+        Some(_) => Err(USAGE.to_string()),
+    };
+
+    if let Err(err) = result {
+        eprintln!("{}", err);
+        std::process::exit(1);
+    }
+}
+
+fn parse_depth(arg: Option<&String>) -> Result<u32, String> {
+    arg.ok_or(USAGE.to_string())?
+        .parse()
+        .map_err(|_| format!("invalid depth: {}", arg.unwrap()))
+}
+
+fn run_perft(args: &[String], split: bool) -> Result<(), String> {
+    let depth = parse_depth(args.first())?;
+    if split && depth == 0 {
+        return Err("divide needs a depth of at least 1".to_string());
+    }
+    let mut board = if args.len() > 1 {
+        Board::from_fen(&args[1..].join(" ")).map_err(|e| e.to_string())?
+    } else {
+        Board::default()
+    };
+    let lookup = LookUp::init();
 
+    let start = Instant::now();
+    let nodes = if split {
+        let moves = divide(&mut board, &lookup, depth);
+        for &(mv, nodes) in &moves {
+            println!("{}: {}", uci_for(&board, mv), nodes);
+        }
+        println!();
+        moves.iter().map(|(_, nodes)| nodes).sum()
+    } else {
+        perft(&mut board, &lookup, depth)
+    };
+    let elapsed = start.elapsed();
+
+    println!("Nodes searched: {}", nodes);
+    println!(
+        "Time: {} ms ({:.0} nps)",
+        elapsed.as_millis(),
+        nodes as f64 / elapsed.as_secs_f64()
+    );
+    Ok(())
+}
+
+fn run_suite(args: &[String]) -> Result<(), String> {
+    let max_depth = match args.first() {
+        Some(_) => parse_depth(args.first())?,
+        None => u32::MAX,
+    };
+    for_each_backend(&mut CheckSliders)?;
     let lookup = LookUp::init();
+
+    let mut failures = 0;
+    for case in SUITE {
+        let mut board = Board::from_fen(case.fen).map_err(|e| e.to_string())?;
+        for &(depth, expected) in case.counts.iter().filter(|(d, _)| *d <= max_depth) {
+            let nodes = perft(&mut board, &lookup, depth);
+            let status = if nodes == expected { "ok" } else { "FAIL" };
+            if nodes != expected {
+                failures += 1;
+            }
+            println!(
+                "{:<4} {:<28} depth {} {:>10} (expected {})",
+                status, case.name, depth, nodes, expected
+            );
+        }
+    }
+
+    // Perft can't tell a position from its mirror images
+    for case in SUITE {
+        let board = Board::from_fen(case.fen).map_err(|e| e.to_string())?;
+        let Some(&(depth, expected)) = case.counts.iter().find(|(d, _)| *d <= max_depth) else {
+            continue;
+        };
+        if board.mirrored().mirrored() != board {
+            return Err(format!("{} changed after mirroring twice", case.fen));
+        }
+        let mut mirrors = vec![("mirrored", board.mirrored())];
+        if let Some(mirror) = board.mirrored_horizontally() {
+            mirrors.push(("mirrored horizontally", mirror));
+        }
+        for (how, mut mirror) in mirrors {
+            let nodes = perft(&mut mirror, &lookup, depth);
+            if nodes != expected {
+                failures += 1;
+                println!(
+                    "FAIL {:<28} {} depth {} {:>10} (expected {})",
+                    case.name, how, depth, nodes, expected
+                );
+            }
+        }
+    }
+
+    if failures > 0 {
+        return Err(format!("{} perft counts did not match", failures));
+    }
+    println!("all perft counts match, mirrored too");
+
+    for case in SUITE {
+        check_game(case.fen, &lookup).map_err(|e| format!("{}: {}", case.name, e))?;
+    }
+    println!(
+        "all {} games replay through push, pop, redo and goto",
+        SUITE.len()
+    );
+
+    let mut positions = 0;
+    for case in SUITE {
+        let mut board = Board::from_fen(case.fen).map_err(|e| e.to_string())?;
+        if board.to_fen() != case.fen {
+            return Err(format!(
+                "{} was written back as {}",
+                case.fen,
+                board.to_fen()
+            ));
+        }
+        positions += check_fen_round_trip(&mut board, &lookup, max_depth.min(FEN_DEPTH))?;
+    }
+    println!("{} positions survive a FEN round trip", positions);
+
+    let mut positions = 0;
+    for case in SUITE {
+        let mut board = Board::from_fen(case.fen).map_err(|e| e.to_string())?;
+        positions += check_staged_generation(&mut board, &lookup, max_depth.min(FEN_DEPTH))?;
+    }
+    println!("{} positions generate the same moves staged", positions);
+    Ok(())
+}
+
+const FEN_DEPTH: u32 = 3;
+
+// from_fen(to_fen(b)) == b and to_fen(from_fen(s)) == s for everything reachable within depth
+fn check_fen_round_trip(board: &mut Board, lookup: &LookUp, depth: u32) -> Result<u64, String> {
+    let fen = board.to_fen();
+    let parsed = Board::from_fen(&fen).map_err(|e| format!("{}: {}", fen, e))?;
+    if parsed != *board || parsed.to_fen() != fen {
+        return Err(format!("FEN round trip failed for {}", fen));
+    }
+    if depth == 0 {
+        return Ok(1);
+    }
+
+    let mut positions = 1;
+    for &mv in board.legal_moves(lookup).iter() {
+        let undo = board.make_move(mv);
+        positions += check_fen_round_trip(board, lookup, depth - 1)?;
+        board.unmake_move(mv, &undo);
+    }
+    Ok(positions)
+}
+
+// Full board, then two masks that split it, so restricted targets get exercised too
+const STAGED_TARGETS: [Bitboard; 3] = [
+    Bitboard::FULL,
+    Bitboard::LIGHT_SQUARES,
+    Bitboard::DARK_SQUARES,
+];
+
+// The square a staged generator's targets mask has to contain for it to produce mv
+fn staged_target(mv: Move) -> Square {
+    if mv.is_en_passant() {
+        Square::from_index(mv.to() as usize ^ 8)
+    } else if mv.is_castle() {
+        mv.castling_king_to()
+    } else {
+        mv.to()
+    }
+}
+
+// Captures plus quiets, or evasions in check, run through is_legal give exactly the
+// legal moves onto targets, each once, for everything reachable within depth
+fn check_staged_generation(board: &mut Board, lookup: &LookUp, depth: u32) -> Result<u64, String> {
+    let legal = board.legal_moves(lookup);
+    for targets in STAGED_TARGETS {
+        let mut staged = MoveList::new();
+        if board.in_check(lookup) {
+            board.generate_evasions(lookup, targets, &mut staged);
+        } else {
+            board.generate_captures(lookup, targets, &mut staged);
+            board.generate_quiets(lookup, targets, &mut staged);
+        }
+        let staged: Vec<Move> = staged
+            .iter()
+            .copied()
+            .filter(|&mv| board.is_legal(lookup, mv))
+            .collect();
+        let unique: HashSet<Move> = staged.iter().copied().collect();
+        let expected: HashSet<Move> = legal
+            .iter()
+            .copied()
+            .filter(|&mv| targets.contains(staged_target(mv)))
+            .collect();
+        if unique.len() != staged.len() || unique != expected {
+            return Err(format!(
+                "staged generation onto {:016x} gives {:?} in {}, legal_moves {:?}",
+                targets.0,
+                staged,
+                board.to_fen(),
+                expected
+            ));
+        }
+    }
+    if depth == 0 {
+        return Ok(1);
+    }
+
+    let mut positions = 1;
+    for &mv in legal.iter() {
+        let undo = board.make_move(mv);
+        positions += check_staged_generation(board, lookup, depth - 1)?;
+        board.unmake_move(mv, &undo);
+    }
+    Ok(positions)
+}
+
+const GAME_PLIES: usize = 16;
+
+// The board and keys `ply` moves into `line`, played from scratch
+fn replay(fen: &str, line: &[Move], ply: usize) -> (Board, Vec<u64>) {
+    let mut board = Board::from_fen(fen).unwrap();
+    let mut keys = Vec::new();
+    for &mv in &line[..ply] {
+        keys.push(board.key);
+        board.make_move(mv);
+    }
+    (board, keys)
+}
+
+fn check_game_at(game: &Game, fen: &str, line: &[Move], ply: usize) -> Result<(), String> {
+    let (board, keys) = replay(fen, line, ply);
+    if game.ply() != ply || game.moves() != &line[..ply] {
+        return Err(format!("at ply {} after {:?}", ply, game.moves()));
+    }
+    if *game.board() != board || game.keys() != keys {
+        return Err(format!("board or keys differ from a replay at ply {}", ply));
+    }
+    Ok(())
+}
+
+// Walks a line back and forth through Game and compares every stop with a replay
+fn check_game(fen: &str, lookup: &LookUp) -> Result<(), String> {
+    let mut game = Game::from_fen(fen).map_err(|e| e.to_string())?;
+    let mut line = Vec::new();
+    while line.len() < GAME_PLIES {
+        let legal = game.board().legal_moves(lookup);
+        if legal.is_empty() {
+            break;
+        }
+        let mv = legal[line.len() * 7 % legal.len()];
+        game.push(mv);
+        line.push(mv);
+    }
+    let end = line.len();
+    check_game_at(&game, fen, &line, end)?;
+
+    for ply in (0..end).rev() {
+        if !game.goto(ply) {
+            return Err(format!("goto({}) refused", ply));
+        }
+        check_game_at(&game, fen, &line, ply)?;
+        if game.redo_moves() != &line[ply..] {
+            return Err(format!("redo line lost at ply {}", ply));
+        }
+        game.goto(end);
+        check_game_at(&game, fen, &line, end)?;
+    }
+
+    if game.goto(end + 1) {
+        return Err(format!("goto({}) went past the end", end + 1));
+    }
+    check_game_at(&game, fen, &line, end)?;
+
+    let mid = end / 2;
+    while game.ply() > mid {
+        let popped = game.pop();
+        if popped != Some(line[game.ply()]) {
+            return Err(format!("pop gave {:?} at ply {}", popped, game.ply()));
+        }
+    }
+    check_game_at(&game, fen, &line, mid)?;
+    if mid < end {
+        let redone = game.redo();
+        if redone != Some(line[mid]) {
+            return Err(format!("redo gave {:?} at ply {}", redone, mid));
+        }
+        check_game_at(&game, fen, &line, mid + 1)?;
+        game.pop();
+    }
+
+    // A different move from here replaces the rest of the line
+    let legal = game.board().legal_moves(lookup);
+    if let Some(&mv) = legal.iter().find(|&&mv| line.get(mid) != Some(&mv)) {
+        game.push(mv);
+        line.truncate(mid);
+        line.push(mv);
+        check_game_at(&game, fen, &line, mid + 1)?;
+        if !game.redo_moves().is_empty() || game.redo().is_some() || game.goto(mid + 2) {
+            return Err("push kept the old redo line".to_string());
+        }
+    }
+
+    while game.pop().is_some() {}
+    check_game_at(&game, fen, &line, 0)
+}
+
+fn uci_for(board: &Board, mv: Move) -> String {
+    if board.chess960() {
+        mv.to_uci_chess960()
+    } else {
+        mv.to_uci()
+    }
+}
+
+// Perft below this many leaves per suite position, so a bench run takes seconds
+const BENCH_NODES: u64 = 5_000_000;
+const BENCH_LOOKUPS: u32 = 50_000_000;
+
+fn run_bench() -> Result<(), String> {
+    for_each_backend(&mut Bench)
+}
+
+// Something to do with a LookUp for each slider backend
+trait BackendVisitor {
+    fn visit<S: SliderAttacks>(&mut self, name: &str, lookup: &LookUp<S>) -> Result<(), String>;
+}
+
+// Every backend this build and CPU have
+fn for_each_backend(visitor: &mut impl BackendVisitor) -> Result<(), String> {
+    visitor.visit("magic", &LookUp::with_sliders(Magic))?;
+    #[cfg(all(feature = "pext", target_arch = "x86_64"))]
+    if let Some(pext) = abdo_chess::pext::Pext::detect() {
+        visitor.visit("pext", &LookUp::with_sliders(pext))?;
+    }
+    visitor.visit("classical", &LookUp::with_sliders(Classical))?;
+    visitor.visit("kogge-stone", &LookUp::with_sliders(KoggeStone))?;
+    visitor.visit("hyperbola", &LookUp::with_sliders(Hyperbola))
+}
+
+struct Bench;
+
+impl BackendVisitor for Bench {
+    fn visit<S: SliderAttacks>(&mut self, name: &str, lookup: &LookUp<S>) -> Result<(), String> {
+        println!("{}", name);
+        bench_perft(lookup)?;
+        bench_sliders(lookup);
+        Ok(())
+    }
+}
+
+fn bench_perft<S: SliderAttacks>(lookup: &LookUp<S>) -> Result<(), String> {
+    let mut total_nodes = 0;
+    let mut expected_nodes = 0;
+    let start = Instant::now();
+    for case in SUITE {
+        let mut board = Board::from_fen(case.fen).map_err(|e| e.to_string())?;
+        let Some(&(depth, expected)) = case.counts.iter().rfind(|(_, nodes)| *nodes <= BENCH_NODES)
+        else {
+            continue;
+        };
+        total_nodes += perft(&mut board, lookup, depth);
+        expected_nodes += expected;
+    }
+    let elapsed = start.elapsed();
+    if total_nodes != expected_nodes {
+        return Err(format!(
+            "perft found {} nodes, expected {}",
+            total_nodes, expected_nodes
+        ));
+    }
+    println!(
+        "  perft   {:>11} nodes {:>6} ms {:>12.0} nps",
+        total_nodes,
+        elapsed.as_millis(),
+        total_nodes as f64 / elapsed.as_secs_f64()
+    );
+    Ok(())
+}
+
+// Slider lookups alone, on pseudo-random squares and occupancies
+fn bench_sliders<S: SliderAttacks>(lookup: &LookUp<S>) {
+    let mut state = 0x9e3779b97f4a7c15u64;
+    let mut acc = Bitboard::EMPTY;
+    let start = Instant::now();
+    for _ in 0..BENCH_LOOKUPS {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        let sq = Square::from_index((state >> 58) as usize);
+        let occupancy = Bitboard(state & state.rotate_left(23));
+        acc ^= lookup.queen_moves(sq, black_box(occupancy));
+    }
+    black_box(acc);
+    let elapsed = start.elapsed();
+    println!(
+        "  sliders {:>11} lookups {:>4} ms {:>8.1} ns/lookup",
+        BENCH_LOOKUPS,
+        elapsed.as_millis(),
+        elapsed.as_nanos() as f64 / BENCH_LOOKUPS as f64
+    );
+}
+
+struct CheckSliders;
+
+impl BackendVisitor for CheckSliders {
+    fn visit<S: SliderAttacks>(&mut self, name: &str, lookup: &LookUp<S>) -> Result<(), String> {
+        let checked = check_sliders(lookup).map_err(|e| format!("{}: {}", name, e))?;
+        println!("{} slider attacks match for {}", checked, name);
+        Ok(())
+    }
+}
+
+type SliderMask = fn(Square) -> Bitboard;
+type SliderLookup<S> = fn(&LookUp<S>, Square, Bitboard) -> Bitboard;
+type SliderReference = fn(Square, Bitboard) -> Bitboard;
+
+// Every subset of each square's relevant occupancy, once alone and once with every
+// other square filled so the masking is checked too, against the ray walkers
+fn check_sliders<S: SliderAttacks>(lookup: &LookUp<S>) -> Result<u64, String> {
+    let pieces: [(&str, SliderMask, SliderLookup<S>, SliderReference); 2] = [
+        ("bishop", bishop_mask, LookUp::bishop_moves, bishop_attacks),
+        ("rook", rook_mask, LookUp::rook_moves, rook_attacks),
+    ];
+    let mut checked = 0;
+    for sq in Square::ALL {
+        for (name, mask, moves, reference) in pieces {
+            let mask = mask(sq);
+            let outside = !mask ^ sq.bitboard();
+            let mut subset = Bitboard::EMPTY;
+            loop {
+                for occupancy in [subset, subset | outside] {
+                    if moves(lookup, sq, occupancy) != reference(sq, occupancy) {
+                        return Err(format!(
+                            "{} on {} with occupancy {:016x} doesn't match the ray walk",
+                            name, sq, occupancy.0
+                        ));
+                    }
+                    checked += 1;
+                }
+                subset = Bitboard(subset.0.wrapping_sub(mask.0) & mask.0);
+                if subset.is_empty() {
+                    break;
+                }
+            }
+        }
+    }
+    Ok(checked)
 }
@@ -0,0 +1,177 @@
+use crate::bitboards::{Board, CastlingRights, Color, Piece, PieceType, Square};
+use crate::moves::Move;
+use crate::zobrist::Zobrist;
+
+// Everything make_move throws away that can't be recomputed from the move itself
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Undo {
+    pub captured: Option<Piece>,
+    pub castling_rights: CastlingRights,
+    pub en_passant: Option<Square>,
+    pub halfmove_clock: u16,
+    // Saturates at u16::MAX, so taking one off on unmake isn't enough
+    pub fullmove_clock: u16,
+    pub key: u64,
+}
+
+impl Board {
+    fn put_piece(&mut self, sq: Square, piece: Piece) {
+        let bb = sq.bitboard();
+        self.squares[sq as usize] = Some(piece);
+        self.key ^= Zobrist::get().piece(piece, sq);
+        self.pieces[piece.r#type as usize] |= bb;
+        self.colors[piece.color as usize] |= bb;
+    }
+
+    fn remove_piece(&mut self, sq: Square) -> Piece {
+        let bb = sq.bitboard();
+        let piece = self.squares[sq as usize]
+            .take()
+            .unwrap_or_else(|| panic!("no piece on {:?}", sq));
+        self.key ^= Zobrist::get().piece(piece, sq);
+        self.pieces[piece.r#type as usize] &= !bb;
+        self.colors[piece.color as usize] &= !bb;
+        piece
+    }
+
+    fn move_piece(&mut self, from: Square, to: Square) {
+        let piece = self.remove_piece(from);
+        self.put_piece(to, piece);
+    }
+
+    // Rights lost when a piece moves from or to a king or castling rook square
+    fn castling_rights_lost(&self, from: Square, to: Square) -> CastlingRights {
+        let touched = from.bitboard() | to.bitboard();
+        let mut lost = CastlingRights::NONE;
+        for (idx, &file) in self.castling_rook_files.iter().enumerate() {
+            let right = CastlingRights::from_bits_truncate(1 << idx);
+            let color = if idx < 2 { Color::White } else { Color::Black };
+            let rank = if idx < 2 { 0 } else { 7 };
+            let king = self.pieces[PieceType::King as usize] & self.colors[color as usize];
+            let rook = Square::from_coords(file, rank).unwrap();
+            if !(touched & (king | rook.bitboard())).is_empty() {
+                lost |= right;
+            }
+        }
+        lost
+    }
+
+    pub fn make_move(&mut self, mv: Move) -> Undo {
+        let from = mv.from();
+        let to = mv.to();
+        let us = self.active_color;
+        let undo = Undo {
+            captured: None,
+            castling_rights: self.castling_rights,
+            en_passant: self.en_passant,
+            halfmove_clock: self.halfmove_clock,
+            fullmove_clock: self.fullmove_clock,
+            key: self.key,
+        };
+
+        let lost = self.castling_rights_lost(from, to);
+        // Whether it counts depends on the pieces, so take it out before they move
+        let en_passant_before = self.en_passant_target();
+        let captured = if mv.is_en_passant() {
+            Some(self.remove_piece(en_passant_victim(to)))
+        } else if mv.is_capture() {
+            Some(self.remove_piece(to))
+        } else {
+            None
+        };
+
+        let moving = self.squares[from as usize]
+            .unwrap_or_else(|| panic!("no piece on {:?}", from))
+            .r#type;
+        if mv.is_castle() {
+            // Lift both first, in Chess960 either may land where the other stood
+            let king = self.remove_piece(from);
+            let rook = self.remove_piece(to);
+            self.put_piece(mv.castling_king_to(), king);
+            self.put_piece(mv.castling_rook_to(), rook);
+        } else {
+            self.move_piece(from, to);
+        }
+        if let Some(promotion) = mv.promotion() {
+            self.remove_piece(to);
+            self.put_piece(
+                to,
+                Piece {
+                    r#type: promotion,
+                    color: us,
+                },
+            );
+        }
+
+        let keys = Zobrist::get();
+        self.key ^= keys.en_passant(en_passant_before) ^ keys.castling(self.castling_rights);
+        self.en_passant = if mv.is_double_push() {
+            Some(Square::from_index((from.index() + to.index()) / 2))
+        } else {
+            None
+        };
+        self.castling_rights &= !lost;
+        self.key ^= keys.castling(self.castling_rights) ^ keys.black_to_move;
+
+        if moving == PieceType::Pawn || captured.is_some() {
+            self.halfmove_clock = 0;
+        } else {
+            self.halfmove_clock = self.halfmove_clock.saturating_add(1);
+        }
+        if us == Color::Black {
+            self.fullmove_clock = self.fullmove_clock.saturating_add(1);
+        }
+        self.active_color = us.opposite();
+        self.key ^= keys.en_passant(self.en_passant_target());
+
+        debug_assert_eq!(self.validate(), Ok(()), "after make_move({:?})", mv);
+        Undo { captured, ..undo }
+    }
+
+    pub fn unmake_move(&mut self, mv: Move, undo: &Undo) {
+        let from = mv.from();
+        let to = mv.to();
+        let us = self.active_color.opposite();
+        self.active_color = us;
+
+        if mv.is_promotion() {
+            self.remove_piece(to);
+            self.put_piece(
+                from,
+                Piece {
+                    r#type: PieceType::Pawn,
+                    color: us,
+                },
+            );
+        } else if mv.is_castle() {
+            let king = self.remove_piece(mv.castling_king_to());
+            let rook = self.remove_piece(mv.castling_rook_to());
+            self.put_piece(from, king);
+            self.put_piece(to, rook);
+        } else {
+            self.move_piece(to, from);
+        }
+
+        if let Some(captured) = undo.captured {
+            let sq = if mv.is_en_passant() {
+                en_passant_victim(to)
+            } else {
+                to
+            };
+            self.put_piece(sq, captured);
+        }
+
+        self.castling_rights = undo.castling_rights;
+        self.en_passant = undo.en_passant;
+        self.halfmove_clock = undo.halfmove_clock;
+        self.fullmove_clock = undo.fullmove_clock;
+        self.key = undo.key;
+
+        debug_assert_eq!(self.validate(), Ok(()), "after unmake_move({:?})", mv);
+    }
+}
+
+// The pawn taken en passant sits behind the target square, on the mover's rank
+fn en_passant_victim(to: Square) -> Square {
+    Square::from_index(to.index() ^ 8)
+}
@@ -1,9 +1,9 @@
-use num_traits::PrimInt;
-use rand::{Rng, SeedableRng};
+use rand::Rng;
+use rand_xoshiro::Xoshiro256PlusPlus;
 
-use crate::bitboards::Color;
-const NOT_A_FILE: u64 = 0xfefefefefefefefe; // ~0x0101010101010101
-const NOT_H_FILE: u64 = 0x7f7f7f7f7f7f7f7f; // ~0x8080808080808080
+use crate::bitboards::{Bitboard, Board, CastlingRights, Color, PieceType, Square};
+use crate::moves::{Move, MoveFlag, MoveList};
+use crate::sliders::{Detected, SliderAttacks};
 
 #[rustfmt::skip]
 pub const ROOK_BITS: [u64; 64] = [
@@ -29,187 +29,151 @@ pub const BISHOP_BITS: [u64; 64] = [
   6, 5, 5, 5, 5, 5, 5, 6
 ];
 
-pub struct LookUp {
-    pub king_attacks: [u64; 64],
-    pub knight_attacks: [u64; 64],
-    pub pawn_attacks: [[u64; 64]; 2],
-    pub bishop_attacks: [[u64; 512]; 64],
-    pub rook_attacks: [[u64; 4096]; 64],
+pub struct LookUp<S: SliderAttacks = Detected> {
+    pub king_attacks: [Bitboard; 64],
+    pub knight_attacks: [Bitboard; 64],
+    pub pawn_attacks: [[Bitboard; 64]; 2],
+    pub between: [[Bitboard; 64]; 64],
+    pub line: [[Bitboard; 64]; 64],
+    pub sliders: S,
 }
 
 impl LookUp {
+    // Magics, or PEXT where the build and CPU have it
     pub fn init() -> LookUp {
-        let magics = MagicNumbers::init();
-        let mut king_attacks_mask: [u64; 64] = [0; 64];
-        let mut knight_attacks_mask: [u64; 64] = [0; 64];
-        let mut pawn_attacks_mask: [[u64; 64]; 2] = [[0; 64]; 2];
-        let mut bishop_attacks_mask: [[u64; 512]; 64] = [[0u64; 512]; 64];
-        let mut rook_attacks_mask: [[u64; 4096]; 64] = [[0u64; 4096]; 64];
-
-        let mut sq_bb: u64 = 1;
-        for sq in 0..64 {
-            king_attacks_mask[sq] = king_attacks(sq_bb);
-            knight_attacks_mask[sq] = knight_attacks(sq_bb);
+        LookUp::with_sliders(Detected::default())
+    }
+
+    // Switches slider lookups between PEXT and magics. PEXT is the default where it's
+    // available, returns whether it's in use now.
+    pub fn use_pext(&mut self, enabled: bool) -> bool {
+        self.sliders.use_pext(enabled)
+    }
+
+    pub fn uses_pext(&self) -> bool {
+        self.sliders.uses_pext()
+    }
+}
+
+impl<S: SliderAttacks> LookUp<S> {
+    // Slider tables, if the backend has any, are built at compile time. This only
+    // fills the cheap rest.
+    pub fn with_sliders(sliders: S) -> LookUp<S> {
+        let mut king_attacks_mask = [Bitboard::EMPTY; 64];
+        let mut knight_attacks_mask = [Bitboard::EMPTY; 64];
+        let mut pawn_attacks_mask = [[Bitboard::EMPTY; 64]; 2];
+
+        for sq in Square::ALL {
+            let sq_bb = sq.bitboard();
+            let idx = sq as usize;
+            king_attacks_mask[idx] = king_attacks(sq_bb);
+            knight_attacks_mask[idx] = knight_attacks(sq_bb);
             let white_attacks = w_pawn_east_attacks(sq_bb) | w_pawn_west_attacks(sq_bb);
             let black_attacks = b_pawn_east_attacks(sq_bb) | b_pawn_west_attacks(sq_bb);
-            pawn_attacks_mask[Color::White as usize][sq] = white_attacks;
-            pawn_attacks_mask[Color::Black as usize][sq] = black_attacks;
-
-            let occupancy_masks = generate_bishop_occupancy_masks(sq as u64); // Generate bishop occupancy masks
-            for &occupancy in &occupancy_masks {
-                let index = transform(occupancy, magics.bishop_magics[sq], BISHOP_BITS[sq]);
-                bishop_attacks_mask[sq][index as usize] = bishop_attacks(sq as u64, occupancy);
-            }
+            pawn_attacks_mask[Color::White as usize][idx] = white_attacks;
+            pawn_attacks_mask[Color::Black as usize][idx] = black_attacks;
+        }
 
-            let occupancy_masks = generate_rook_occupancy_masks(sq as u64); // Generate rook occupancy masks
-            for &occupancy in &occupancy_masks {
-                let index = transform(occupancy, magics.rook_magics[sq], ROOK_BITS[sq]);
-                rook_attacks_mask[sq][index as usize] = rook_attacks(sq as u64, occupancy);
+        let mut between = [[Bitboard::EMPTY; 64]; 64];
+        let mut line = [[Bitboard::EMPTY; 64]; 64];
+        for a in Square::ALL {
+            for b in Square::ALL {
+                if a == b {
+                    continue;
+                }
+                let (a_bb, b_bb) = (a.bitboard(), b.bitboard());
+                if rook_attacks(a, Bitboard::EMPTY).contains(b) {
+                    between[a as usize][b as usize] = rook_attacks(a, b_bb) & rook_attacks(b, a_bb);
+                    line[a as usize][b as usize] = (rook_attacks(a, Bitboard::EMPTY)
+                        & rook_attacks(b, Bitboard::EMPTY))
+                        | a_bb
+                        | b_bb;
+                } else if bishop_attacks(a, Bitboard::EMPTY).contains(b) {
+                    between[a as usize][b as usize] =
+                        bishop_attacks(a, b_bb) & bishop_attacks(b, a_bb);
+                    line[a as usize][b as usize] = (bishop_attacks(a, Bitboard::EMPTY)
+                        & bishop_attacks(b, Bitboard::EMPTY))
+                        | a_bb
+                        | b_bb;
+                }
             }
-            sq_bb <<= 1;
         }
 
         LookUp {
             king_attacks: king_attacks_mask,
             knight_attacks: knight_attacks_mask,
             pawn_attacks: pawn_attacks_mask,
-            bishop_attacks: bishop_attacks_mask,
-            rook_attacks: rook_attacks_mask,
+            between,
+            line,
+            sliders,
         }
     }
-}
-use rand_xoshiro::Xoshiro256PlusPlus;
-pub struct MagicNumbers {
-    bishop_magics: [u64; 64],
-    rook_magics: [u64; 64],
-}
-
-impl MagicNumbers {
-    pub fn init() -> MagicNumbers {
-        let mut bishop_magics = [0u64; 64];
-        let mut rook_magics = [0u64; 64];
 
-        for sq in 0..64 {
-            let sq_bb = 1u64 << sq;
-            bishop_magics[sq] = find_magic(sq_bb, BISHOP_BITS[sq], true);
-            rook_magics[sq] = find_magic(sq_bb, ROOK_BITS[sq], false);
-        }
-
-        MagicNumbers {
-            bishop_magics,
-            rook_magics,
-        }
+    #[inline(always)]
+    pub fn bishop_moves(&self, sq: Square, occupancy: Bitboard) -> Bitboard {
+        self.sliders.bishop_moves(sq, occupancy)
     }
-}
-
-fn generate_bishop_occupancy_masks(square: u64) -> Vec<u64> {
-    let mut mask = 0u64;
-    let rank = square / 8;
-    let file = square % 8;
 
-    // Diagonals: up-right, up-left, down-right, down-left
-    // up-right
-    for (r, f) in (rank + 1..8).zip(file + 1..8) {
-        mask |= 1u64 << (f + r * 8);
+    #[inline(always)]
+    pub fn rook_moves(&self, sq: Square, occupancy: Bitboard) -> Bitboard {
+        self.sliders.rook_moves(sq, occupancy)
     }
-    // up-left
-    for (r, f) in (rank + 1..8).zip((0..file).rev()) {
-        mask |= 1u64 << (f + r * 8);
-    }
-    // down-right
-    for (r, f) in (0..rank).rev().zip(file + 1..8) {
-        mask |= 1u64 << (f + r * 8);
-    }
-    // down-left
-    for (r, f) in (0..rank).rev().zip((0..file).rev()) {
-        mask |= 1u64 << (f + r * 8);
-    }
-
-    let mut occupancy_masks = vec![];
-    let bit_count = mask.count_ones() as usize;
-    let combinations = 1 << bit_count;
-
-    for i in 0..combinations {
-        let mut occupancy = 0u64;
-        for j in 0..bit_count {
-            if i & (1 << j) != 0 {
-                occupancy |= 1u64 << (mask.trailing_zeros() as u64 + j as u64);
-            }
-        }
-        occupancy_masks.push(occupancy);
-    }
-
-    occupancy_masks
-}
-fn generate_rook_occupancy_masks(square: u64) -> Vec<u64> {
-    let mut mask = 0u64;
-    let rank = square / 8;
-    let file = square % 8;
-
-    // Horizontal and vertical lines
-    // Horizontal
-    for f in (0..file).rev() {
-        mask |= 1u64 << (f + rank * 8);
-    }
-    for f in file + 1..8 {
-        mask |= 1u64 << (f + rank * 8);
-    }
-    // Vertical
-    for r in (0..rank).rev() {
-        mask |= 1u64 << (file + r * 8);
-    }
-    for r in rank + 1..8 {
-        mask |= 1u64 << (file + r * 8);
-    }
-
-    let mut occupancy_masks = vec![];
-    let bit_count = mask.count_ones() as usize;
-    let combinations = 1 << bit_count;
 
-    for i in 0..combinations {
-        let mut occupancy = 0u64;
-        for j in 0..bit_count {
-            if i & (1 << j) != 0 {
-                occupancy |= 1u64 << (mask.trailing_zeros() as u64 + j as u64);
-            }
-        }
-        occupancy_masks.push(occupancy);
+    #[inline(always)]
+    pub fn queen_moves(&self, sq: Square, occupancy: Bitboard) -> Bitboard {
+        self.sliders.queen_moves(sq, occupancy)
     }
-
-    occupancy_masks
 }
 
-pub fn find_magic(square: u64, mask_bits: u64, is_bishop: bool) -> u64 {
-    let mask: u64 = if is_bishop {
+// Searches for a magic that maps every occupancy of the square's mask to an index
+// of mask_bits bits without two different attack sets colliding. mask_bits may be
+// below the mask's popcount, then only identical attack sets can share a slot.
+// None if nothing turned up within attempts. Used offline by magicgen, the
+// engine's magics are the constants in magics.
+pub fn find_magic(
+    square: Square,
+    mask_bits: u64,
+    is_bishop: bool,
+    rng: &mut Xoshiro256PlusPlus,
+    attempts: u64,
+) -> Option<u64> {
+    let mask = if is_bishop {
         bishop_mask(square)
     } else {
         rook_mask(square)
     };
-    let occupancy_count = mask.count_ones() as u64;
-    let occupation_indices = 1 << occupancy_count;
-    let mut rng = Xoshiro256PlusPlus::seed_from_u64(square);
-
-    // Iterate with a larger number of attempts for more robust results
-    for attempt in 0..1_000_000_000_000_i64 {
-        let magic = random_magic_number(&mut rng);
+    let occupancy_count = mask.popcount() as u64;
+    let occupancies: Vec<Bitboard> = (0..1 << occupancy_count)
+        .map(|i| index_to_bitboard(i, occupancy_count, mask))
+        .collect();
+    let attacks: Vec<Bitboard> = occupancies
+        .iter()
+        .map(|&occupancy| {
+            if is_bishop {
+                bishop_attacks(square, occupancy)
+            } else {
+                rook_attacks(square, occupancy)
+            }
+        })
+        .collect();
+
+    // Slots are stamped with the attempt that filled them, so the table never
+    // needs clearing between attempts
+    let mut used_attacks = vec![Bitboard::EMPTY; 1 << mask_bits];
+    let mut used_in = vec![0u64; 1 << mask_bits];
+    for attempt in 1..=attempts {
+        let magic = random_magic_number(rng);
         // Ensure the magic number has enough leading zero bits
-        if (mask.wrapping_mul(magic) & 0xFF00000000000000).count_ones() < 6 {
+        if (mask.0.wrapping_mul(magic) & 0xFF00000000000000).count_ones() < 6 {
             continue;
         }
 
-        let mut used_attacks = vec![0u64; 1 << mask_bits];
         let mut fail = false;
-
-        for i in 0..occupation_indices {
-            let occupancy = index_to_u64(i, occupancy_count, mask);
-            let attacks = if is_bishop {
-                bishop_attacks(square, occupancy)
-            } else {
-                rook_attacks(square, occupancy)
-            };
+        for (&occupancy, &attacks) in occupancies.iter().zip(&attacks) {
             let index = transform(occupancy, magic, mask_bits) as usize;
 
-            if used_attacks[index] == 0 {
+            if used_in[index] != attempt {
+                used_in[index] = attempt;
                 used_attacks[index] = attacks;
             } else if used_attacks[index] != attacks {
                 fail = true;
@@ -218,74 +182,36 @@ pub fn find_magic(square: u64, mask_bits: u64, is_bishop: bool) -> u64 {
         }
 
         if !fail {
-            return magic;
+            return Some(magic);
         }
     }
-
-    eprintln!("MAGIC NUMBER FAILED for square {}", square.trailing_zeros());
-    0
+    None
 }
 
+// Magics with few set bits are far more likely to work, so AND a few draws together
 fn random_magic_number(rng: &mut Xoshiro256PlusPlus) -> u64 {
-    let u1 = rng.gen::<u64>() & 0xFFFF;
-    let u2 = rng.gen::<u64>() & 0xFFFF;
-    let u3 = rng.gen::<u64>() & 0xFFFF;
-    let u4 = rng.gen::<u64>() & 0xFFFF;
-    u1 | (u2 << 16) | (u3 << 32) | (u4 << 48)
+    rng.gen::<u64>() & rng.gen::<u64>() & rng.gen::<u64>()
 }
 
 #[inline(always)]
-pub fn transform(occupancy: u64, magic: u64, bits: u64) -> u64 {
-    (occupancy.wrapping_mul(magic)) >> (64 - bits)
+pub const fn transform(occupancy: Bitboard, magic: u64, bits: u64) -> u64 {
+    (occupancy.0.wrapping_mul(magic)) >> (64 - bits)
 }
 
-pub fn index_to_u64(index: u64, bits: u64, mask: u64) -> u64 {
+// Spreads the low `bits` bits of index over the set squares of mask
+pub const fn index_to_bitboard(index: u64, bits: u64, mask: Bitboard) -> Bitboard {
     let mut result = 0;
-    let mut bit = 1;
-    for i in 0..bits {
+    let mut mask = mask.0;
+    let mut i = 0;
+    while i < bits {
+        let bit = mask & mask.wrapping_neg();
+        mask ^= bit;
         if index & (1 << i) != 0 {
-            while mask & bit == 0 {
-                bit <<= 1;
-            }
             result |= bit;
         }
-        bit <<= 1;
+        i += 1;
     }
-    result
-}
-pub fn rook_mask(square: u64) -> u64 {
-    let mut attacks: u64 = 0;
-    let rk = (square.trailing_zeros() / 8) as u64;
-    let fl = (square.trailing_zeros() % 8) as u64;
-
-    // North Movement
-    let mut r = rk + 1;
-    while r <= 6 {
-        attacks |= 1u64 << (fl + r * 8);
-        r += 1;
-    }
-
-    // South Movement
-    let mut r = rk as isize - 1;
-    while r >= 1 {
-        attacks |= 1u64 << (fl + r as u64 * 8);
-        r -= 1;
-    }
-
-    // East Movement
-    let mut f = fl + 1;
-    while f <= 6 {
-        attacks |= 1u64 << (f + rk * 8);
-        f += 1;
-    }
-
-    // West Movement
-    let mut f = fl as isize - 1;
-    while f >= 1 {
-        attacks |= 1u64 << (f as u64 + rk * 8);
-        f -= 1;
-    }
-    attacks
+    Bitboard(result)
 }
 
 pub fn random_u64() -> u64 {
@@ -302,214 +228,548 @@ pub fn random_u64() -> u64 {
 pub fn random_u64_few_bits() -> u64 {
     random_u64() & random_u64() & random_u64()
 }
-pub fn rook_attacks(square: u64, block: u64) -> u64 {
-    let mut result: u64 = 0;
-    let sq_index = square.trailing_zeros() as usize;
-    let rank = sq_index / 8;
-    let file = sq_index % 8;
-
-    // Vertical moves - Up
-    for r in (rank + 1)..8 {
-        let pos = file + r * 8;
-        if pos >= 64 {
-            break;
-        } // Ensure pos is within bounds
-        result |= 1u64 << pos;
-        if block & (1u64 << pos) != 0 {
-            break;
-        }
-    }
 
-    // Vertical moves - Down
-    for r in (0..rank).rev() {
-        let pos = file + r * 8;
-        if pos >= 64 {
-            break;
-        } // Ensure pos is within bounds
-        result |= 1u64 << pos;
-        if block & (1u64 << pos) != 0 {
-            break;
+// Shift for one step along the ray and the file a step would wrap onto
+pub(crate) const ROOK_DIRECTIONS: [(i8, u64); 4] = [
+    (8, 0),
+    (-8, 0),
+    (1, Bitboard::FILE_A.0),
+    (-1, Bitboard::FILE_H.0),
+];
+pub(crate) const BISHOP_DIRECTIONS: [(i8, u64); 4] = [
+    (9, Bitboard::FILE_A.0),
+    (7, Bitboard::FILE_H.0),
+    (-7, Bitboard::FILE_A.0),
+    (-9, Bitboard::FILE_H.0),
+];
+
+// Walks each ray out from square, stopping on (and including) the first blocker.
+// const so the magic tables can be built from it at compile time.
+pub(crate) const fn ray_attacks(
+    square: Square,
+    block: Bitboard,
+    directions: &[(i8, u64)],
+) -> Bitboard {
+    let mut attacks = 0;
+    let mut i = 0;
+    while i < directions.len() {
+        let (shift, wrap) = directions[i];
+        let mut bit = 1u64 << square as u8;
+        loop {
+            bit = if shift > 0 {
+                bit << shift
+            } else {
+                bit >> -shift
+            } & !wrap;
+            attacks |= bit;
+            if bit & !block.0 == 0 {
+                break;
+            }
         }
+        i += 1;
     }
+    Bitboard(attacks)
+}
 
-    // Horizontal moves - Right
-    for f in (file + 1)..8 {
-        let pos = f + rank * 8;
-        if pos >= 64 {
-            break;
-        } // Ensure pos is within bounds
-        result |= 1u64 << pos;
-        if block & (1u64 << pos) != 0 {
-            break;
-        }
+// Board edges that can't change the attack set: the last square of a ray is
+// attacked whether or not something stands on it
+const fn relevant_edges(square: Square) -> u64 {
+    let rank = Bitboard::RANK_1.0 << (square as u8 / 8 * 8);
+    let file = Bitboard::FILE_A.0 << (square as u8 % 8);
+    ((Bitboard::RANK_1.0 | Bitboard::RANK_8.0) & !rank)
+        | ((Bitboard::FILE_A.0 | Bitboard::FILE_H.0) & !file)
+}
+
+pub const fn rook_mask(square: Square) -> Bitboard {
+    Bitboard(rook_attacks(square, Bitboard::EMPTY).0 & !relevant_edges(square))
+}
+
+pub const fn rook_attacks(square: Square, block: Bitboard) -> Bitboard {
+    ray_attacks(square, block, &ROOK_DIRECTIONS)
+}
+
+pub const fn bishop_mask(square: Square) -> Bitboard {
+    Bitboard(bishop_attacks(square, Bitboard::EMPTY).0 & !relevant_edges(square))
+}
+
+pub const fn bishop_attacks(square: Square, block: Bitboard) -> Bitboard {
+    ray_attacks(square, block, &BISHOP_DIRECTIONS)
+}
+
+pub fn w_pawn_east_attacks(wpawns: Bitboard) -> Bitboard {
+    wpawns.north_east()
+}
+pub fn w_pawn_west_attacks(wpawns: Bitboard) -> Bitboard {
+    wpawns.north_west()
+}
+
+pub fn b_pawn_east_attacks(bpawns: Bitboard) -> Bitboard {
+    bpawns.south_east()
+}
+pub fn b_pawn_west_attacks(bpawns: Bitboard) -> Bitboard {
+    bpawns.south_west()
+}
+
+pub fn knight_attacks(knights: Bitboard) -> Bitboard {
+    let l1 = knights.west();
+    let l2 = l1.west();
+    let r1 = knights.east();
+    let r2 = r1.east();
+    let h1 = l1 | r1;
+    let h2 = l2 | r2;
+    h1.north().north() | h1.south().south() | h2.north() | h2.south()
+}
+pub fn king_attacks(kingset: Bitboard) -> Bitboard {
+    kingset.east()
+        | kingset.west()
+        | kingset.north()
+        | kingset.south()
+        | kingset.north_east()
+        | kingset.north_west()
+        | kingset.south_east()
+        | kingset.south_west()
+}
+
+const BACK_RANKS: Bitboard = Bitboard(Bitboard::RANK_1.0 | Bitboard::RANK_8.0);
+
+#[inline(always)]
+fn push_moves(list: &mut MoveList, from: Square, targets: Bitboard, enemy: Bitboard) {
+    for to in targets {
+        let flag = if enemy.contains(to) {
+            MoveFlag::Capture
+        } else {
+            MoveFlag::Quiet
+        };
+        list.push(Move::new(from, to, flag));
     }
+}
 
-    // Horizontal moves - Left
-    for f in (0..file).rev() {
-        let pos = f + rank * 8;
-        if pos >= 64 {
-            break;
-        } // Ensure pos is within bounds
-        result |= 1u64 << pos;
-        if block & (1u64 << pos) != 0 {
-            break;
+#[inline(always)]
+fn push_pawn_moves(list: &mut MoveList, from: Square, to: Square, capture: bool) {
+    if BACK_RANKS.contains(to) {
+        for piece in [
+            PieceType::Queen,
+            PieceType::Knight,
+            PieceType::Rook,
+            PieceType::Bishop,
+        ] {
+            list.push(Move::new_promotion(from, to, piece, capture));
         }
+    } else {
+        let flag = if capture {
+            MoveFlag::Capture
+        } else if from.index().abs_diff(to.index()) == 16 {
+            MoveFlag::DoublePush
+        } else {
+            MoveFlag::Quiet
+        };
+        list.push(Move::new(from, to, flag));
     }
+}
 
-    result
+// The square `delta` indices away, for walking pawn moves back to their origin
+#[inline(always)]
+fn shifted(sq: Square, delta: i32) -> Square {
+    Square::from_index((sq as i32 + delta) as usize)
 }
 
-pub fn bishop_mask(square: u64) -> u64 {
-    let mut attacks: u64 = 0;
-    let rk = (square.trailing_zeros() / 8) as u64;
-    let fl = (square.trailing_zeros() % 8) as u64;
-
-    // North East Movement
-    let mut r: isize = rk as isize + 1;
-    let mut f: isize = fl as isize + 1;
-    while r <= 6 && f <= 6 {
-        attacks |= 1u64 << (f as u64 + r as u64 * 8);
-        r += 1;
-        f += 1;
+impl Board {
+    #[inline(always)]
+    pub(crate) fn bb(&self, piece: PieceType, color: Color) -> Bitboard {
+        self.pieces[piece as usize] & self.colors[color as usize]
     }
 
-    // North West Movement
-    r = rk as isize + 1;
-    f = fl as isize - 1;
-    while r <= 6 && f >= 1 {
-        attacks |= 1u64 << (f as u64 + r as u64 * 8);
-        r += 1;
-        f -= 1;
+    // Every square attacked by `by` given the occupancy
+    pub fn attacked_squares<S: SliderAttacks>(
+        &self,
+        lookup: &LookUp<S>,
+        by: Color,
+        occupancy: Bitboard,
+    ) -> Bitboard {
+        let pawns = self.bb(PieceType::Pawn, by);
+        let mut attacks = match by {
+            Color::White => w_pawn_east_attacks(pawns) | w_pawn_west_attacks(pawns),
+            Color::Black => b_pawn_east_attacks(pawns) | b_pawn_west_attacks(pawns),
+        };
+        attacks |= king_attacks(self.bb(PieceType::King, by));
+        attacks |= knight_attacks(self.bb(PieceType::Knight, by));
+
+        let queens = self.bb(PieceType::Queen, by);
+        for sq in self.bb(PieceType::Bishop, by) | queens {
+            attacks |= lookup.bishop_moves(sq, occupancy);
+        }
+        for sq in self.bb(PieceType::Rook, by) | queens {
+            attacks |= lookup.rook_moves(sq, occupancy);
+        }
+        attacks
     }
 
-    // South East Movement
-    r = rk as isize - 1;
-    f = fl as isize + 1;
-    for (r, f) in (1..rk).rev().zip(fl + 1..7) {
-        attacks |= 1u64 << (f + r * 8);
+    pub fn legal_moves<S: SliderAttacks>(&self, lookup: &LookUp<S>) -> MoveList {
+        let mut list = MoveList::new();
+        let us = self.active_color;
+        let them = us.opposite();
+        let own = self.colors[us as usize];
+        let enemy = self.colors[them as usize];
+        let occupancy = own | enemy;
+        let king_bb = self.bb(PieceType::King, us);
+        let king = king_bb.lsb();
+
+        // Lift the king off the board so it can't step back along a checking ray
+        let danger = self.attacked_squares(lookup, them, occupancy ^ king_bb);
+        push_moves(
+            &mut list,
+            king,
+            lookup.king_attacks[king as usize] & !own & !danger,
+            enemy,
+        );
+
+        let checkers = self.attackers_by(lookup, king, occupancy, them);
+        if checkers.more_than_one() {
+            return list;
+        }
+        let check_mask = if !checkers.is_empty() {
+            checkers | lookup.between[king as usize][checkers.lsb() as usize]
+        } else {
+            Bitboard::FULL
+        };
+        let pinned = self.pinned(lookup, us);
+        let pin_mask = |sq: Square| {
+            if pinned.contains(sq) {
+                lookup.line[king as usize][sq as usize]
+            } else {
+                Bitboard::FULL
+            }
+        };
+
+        for sq in self.bb(PieceType::Knight, us) & !pinned {
+            let targets = lookup.knight_attacks[sq as usize] & !own & check_mask;
+            push_moves(&mut list, sq, targets, enemy);
+        }
+
+        let queens = self.bb(PieceType::Queen, us);
+        for sq in self.bb(PieceType::Bishop, us) | queens {
+            let targets = lookup.bishop_moves(sq, occupancy) & !own & check_mask & pin_mask(sq);
+            push_moves(&mut list, sq, targets, enemy);
+        }
+        for sq in self.bb(PieceType::Rook, us) | queens {
+            let targets = lookup.rook_moves(sq, occupancy) & !own & check_mask & pin_mask(sq);
+            push_moves(&mut list, sq, targets, enemy);
+        }
+
+        let (forward, start_rank) = match us {
+            Color::White => (8, Bitboard::rank(1)),
+            Color::Black => (-8, Bitboard::rank(6)),
+        };
+        for sq in self.bb(PieceType::Pawn, us) {
+            let allowed = check_mask & pin_mask(sq);
+
+            let one = shifted(sq, forward);
+            if !occupancy.contains(one) {
+                if allowed.contains(one) {
+                    push_pawn_moves(&mut list, sq, one, false);
+                }
+                if start_rank.contains(sq) {
+                    let two = shifted(one, forward);
+                    if !occupancy.contains(two) && allowed.contains(two) {
+                        push_pawn_moves(&mut list, sq, two, false);
+                    }
+                }
+            }
+
+            for to in lookup.pawn_attacks[us as usize][sq as usize] & enemy & allowed {
+                push_pawn_moves(&mut list, sq, to, true);
+            }
+
+            if let Some(ep) = self.en_passant {
+                if lookup.pawn_attacks[us as usize][sq as usize].contains(ep)
+                    && self.en_passant_is_legal(&lookup.sliders, sq, ep)
+                {
+                    list.push(Move::new(sq, ep, MoveFlag::EnPassant));
+                }
+            }
+        }
+
+        if checkers.is_empty() {
+            self.push_castling_moves(lookup, &mut list, occupancy, danger);
+        }
+
+        list
     }
-    // South West Movement
-    r = rk as isize - 1;
-    f = fl as isize - 1;
-    while r >= 1 && f >= 1 {
-        attacks |= 1u64 << (f as u64 + r as u64 * 8);
-        r -= 1;
-        f -= 1;
+
+    // Castling for both standard chess and Chess960: everything between the king,
+    // the rook and their destinations must be empty apart from those two, and the
+    // king may not pass through or land on an attacked square
+    fn push_castling_moves<S: SliderAttacks>(
+        &self,
+        lookup: &LookUp<S>,
+        list: &mut MoveList,
+        occupancy: Bitboard,
+        danger: Bitboard,
+    ) {
+        let us = self.active_color;
+        let king_bb = self.bb(PieceType::King, us);
+        let king = king_bb.lsb();
+        let rank = match us {
+            Color::White => 0,
+            Color::Black => 7,
+        };
+        let on_rank = |file: u8| Square::from_coords(file, rank).unwrap();
+        for (idx, flag, king_to, rook_to) in [
+            (
+                us as usize * 2,
+                MoveFlag::KingCastle,
+                on_rank(6),
+                on_rank(5),
+            ),
+            (
+                us as usize * 2 + 1,
+                MoveFlag::QueenCastle,
+                on_rank(2),
+                on_rank(3),
+            ),
+        ] {
+            if !self
+                .castling_rights
+                .contains(CastlingRights::from_bits_truncate(1 << idx))
+            {
+                continue;
+            }
+            let rook = on_rank(self.castling_rook_files[idx]);
+            let king_path = lookup.between[king as usize][king_to as usize] | king_to.bitboard();
+            let rook_path = lookup.between[rook as usize][rook_to as usize] | rook_to.bitboard();
+            let castlers = king_bb | rook.bitboard();
+            if !((king_path | rook_path) & occupancy & !castlers).is_empty()
+                || !(king_path & danger).is_empty()
+            {
+                continue;
+            }
+            // A rook off the corner can be what shields the king's destination on the
+            // back rank. That's down to where it stands, not the notation mode.
+            if !matches!(rook.file(), 0 | 7)
+                && !self
+                    .attackers_by(lookup, king_to, occupancy ^ castlers, us.opposite())
+                    .is_empty()
+            {
+                continue;
+            }
+            list.push(Move::new(king, rook, flag));
+        }
     }
-    attacks
 }
-pub fn bishop_attacks(square: u64, block: u64) -> u64 {
-    let mut attacks: u64 = 0;
-    let rank = square.trailing_zeros() / 8;
-    let file = square.trailing_zeros() % 8;
-
-    // Diagonal moves - Up-right
-    for (r, f) in (rank + 1..8).zip(file + 1..8) {
-        let pos = f + r * 8;
-        attacks |= 1u64 << pos;
-        if block & (1u64 << pos) != 0 {
-            break;
-        }
+
+// Staged pseudo-legal generation. Moves may still leave the king in check,
+// run them through is_legal before playing them.
+impl Board {
+    // Captures of enemy pieces on targets, plus every promotion onto targets
+    pub fn generate_captures<S: SliderAttacks>(
+        &self,
+        lookup: &LookUp<S>,
+        targets: Bitboard,
+        list: &mut MoveList,
+    ) {
+        let us = self.active_color;
+        let enemy = self.colors[us.opposite() as usize];
+        let empty = !self.occupancy();
+        self.generate_piece_moves(lookup, targets & enemy, true, list);
+        self.generate_pawn_moves(lookup, targets & enemy, targets & empty & BACK_RANKS, list);
     }
 
-    // Diagonal moves - Up-left
-    for (r, f) in (rank + 1..8).zip((0..file).rev()) {
-        let pos = f + r * 8;
-        attacks |= 1u64 << pos;
-        if block & (1u64 << pos) != 0 {
-            break;
+    // Non-capturing, non-promoting moves onto targets, castling included
+    pub fn generate_quiets<S: SliderAttacks>(
+        &self,
+        lookup: &LookUp<S>,
+        targets: Bitboard,
+        list: &mut MoveList,
+    ) {
+        let empty = !self.occupancy();
+        self.generate_piece_moves(lookup, targets & empty, true, list);
+        self.generate_pawn_moves(lookup, Bitboard::EMPTY, targets & empty & !BACK_RANKS, list);
+
+        let them = self.active_color.opposite();
+        let king = self.bb(PieceType::King, self.active_color).lsb();
+        let occupancy = !empty;
+        if self.attackers_by(lookup, king, occupancy, them).is_empty() {
+            let danger = self.attacked_squares(lookup, them, occupancy);
+            let mut castling = MoveList::new();
+            self.push_castling_moves(lookup, &mut castling, occupancy, danger);
+            for &mv in castling.iter() {
+                if targets.contains(mv.castling_king_to()) {
+                    list.push(mv);
+                }
+            }
         }
     }
 
-    // Diagonal moves - Down-right
-    for (r, f) in (0..rank).rev().zip(file + 1..8) {
-        let pos = f + r * 8;
-        attacks |= 1u64 << pos;
-        if block & (1u64 << pos) != 0 {
-            break;
+    // Moves that might get the king out of check: king steps, and with a single
+    // checker, capturing it or blocking the ray. Only meaningful while in check.
+    pub fn generate_evasions<S: SliderAttacks>(
+        &self,
+        lookup: &LookUp<S>,
+        targets: Bitboard,
+        list: &mut MoveList,
+    ) {
+        let us = self.active_color;
+        let them = us.opposite();
+        let own = self.colors[us as usize];
+        let enemy = self.colors[them as usize];
+        let occupancy = own | enemy;
+        let king_bb = self.bb(PieceType::King, us);
+        let king = king_bb.lsb();
+
+        let danger = self.attacked_squares(lookup, them, occupancy ^ king_bb);
+        push_moves(
+            list,
+            king,
+            lookup.king_attacks[king as usize] & !own & !danger & targets,
+            enemy,
+        );
+
+        let checkers = self.attackers_by(lookup, king, occupancy, them);
+        if checkers.popcount() != 1 {
+            return;
         }
+        let block = targets & (checkers | lookup.between[king as usize][checkers.lsb() as usize]);
+        self.generate_piece_moves(lookup, block & !own, false, list);
+        self.generate_pawn_moves(lookup, block & enemy, block & !occupancy, list);
     }
 
-    // Diagonal moves - Down-left
-    for (r, f) in (0..rank).rev().zip((0..file).rev()) {
-        let pos = f + r * 8;
-        attacks |= 1u64 << pos;
-        if block & (1u64 << pos) != 0 {
-            break;
+    fn generate_piece_moves<S: SliderAttacks>(
+        &self,
+        lookup: &LookUp<S>,
+        targets: Bitboard,
+        include_king: bool,
+        list: &mut MoveList,
+    ) {
+        let us = self.active_color;
+        let enemy = self.colors[us.opposite() as usize];
+        let occupancy = self.occupancy();
+
+        for sq in self.bb(PieceType::Knight, us) {
+            push_moves(
+                list,
+                sq,
+                lookup.knight_attacks[sq as usize] & targets,
+                enemy,
+            );
+        }
+        let queens = self.bb(PieceType::Queen, us);
+        for sq in self.bb(PieceType::Bishop, us) | queens {
+            push_moves(
+                list,
+                sq,
+                lookup.bishop_moves(sq, occupancy) & targets,
+                enemy,
+            );
+        }
+        for sq in self.bb(PieceType::Rook, us) | queens {
+            push_moves(list, sq, lookup.rook_moves(sq, occupancy) & targets, enemy);
+        }
+        if include_king {
+            let king = self.bb(PieceType::King, us).lsb();
+            push_moves(
+                list,
+                king,
+                lookup.king_attacks[king as usize] & targets,
+                enemy,
+            );
         }
     }
 
-    attacks
-}
-pub fn w_pawn_east_attacks(wpawns: u64) -> u64 {
-    no_ea_one(wpawns)
-}
-pub fn w_pawn_west_attacks(wpawns: u64) -> u64 {
-    no_we_one(wpawns)
-}
-
-pub fn b_pawn_east_attacks(bpawns: u64) -> u64 {
-    so_ea_one(bpawns)
-}
-pub fn b_pawn_west_attacks(bpawns: u64) -> u64 {
-    so_we_one(bpawns)
-}
+    // Pawn captures landing on capture_targets and pushes landing on push_targets.
+    // En passant is included when the pawn it takes is in capture_targets.
+    fn generate_pawn_moves<S: SliderAttacks>(
+        &self,
+        lookup: &LookUp<S>,
+        capture_targets: Bitboard,
+        push_targets: Bitboard,
+        list: &mut MoveList,
+    ) {
+        let us = self.active_color;
+        let empty = !self.occupancy();
+        let pawns = self.bb(PieceType::Pawn, us);
+        let (single, double, back, east, west, east_back, west_back) = match us {
+            Color::White => {
+                let single = pawns.north() & empty;
+                let double = (single & Bitboard::rank(2)).north() & empty;
+                (
+                    single,
+                    double,
+                    -8,
+                    w_pawn_east_attacks(pawns),
+                    w_pawn_west_attacks(pawns),
+                    -9,
+                    -7,
+                )
+            }
+            Color::Black => {
+                let single = pawns.south() & empty;
+                let double = (single & Bitboard::rank(5)).south() & empty;
+                (
+                    single,
+                    double,
+                    8,
+                    b_pawn_east_attacks(pawns),
+                    b_pawn_west_attacks(pawns),
+                    7,
+                    9,
+                )
+            }
+        };
 
-fn knight_attacks(knights: u64) -> u64 {
-    let l1 = (knights >> 1) & NOT_H_FILE;
-    let l2 = (knights >> 2) & 0x3f3f3f3f3f3f3f3f;
-    let r1 = (knights << 1) & NOT_A_FILE;
-    let r2 = (knights << 2) & 0xfcfcfcfcfcfcfcfc;
-    let h1 = l1 | r1;
-    let h2 = l2 | r2;
-    (h1 << 16) | (h1 >> 16) | (h2 << 8) | (h2 >> 8)
-}
-pub fn king_attacks(kingset: u64) -> u64 {
-    east_one(kingset)
-        | west_one(kingset)
-        | nort_one(kingset)
-        | sout_one(kingset)
-        | no_ea_one(kingset)
-        | no_we_one(kingset)
-        | so_ea_one(kingset)
-        | so_we_one(kingset)
-}
-pub fn sout_one(b: u64) -> u64 {
-    b >> 8
-}
-pub fn nort_one(b: u64) -> u64 {
-    b << 8
-}
-pub fn east_one(b: u64) -> u64 {
-    (b << 1) & NOT_A_FILE
-}
-pub fn no_ea_one(b: u64) -> u64 {
-    (b << 9) & NOT_A_FILE
-}
-pub fn so_ea_one(b: u64) -> u64 {
-    (b >> 7) & NOT_A_FILE
-}
-pub fn west_one(b: u64) -> u64 {
-    (b >> 1) & NOT_H_FILE
-}
-pub fn count_1s(bb: u64) -> u64 {
-    bb.count_ones() as u64
-}
-pub fn so_we_one(b: u64) -> u64 {
-    (b >> 9) & NOT_H_FILE
-}
-pub fn no_we_one(b: u64) -> u64 {
-    (b << 7) & NOT_H_FILE
-}
+        for to in single & push_targets {
+            push_pawn_moves(list, shifted(to, back), to, false);
+        }
+        for to in double & push_targets {
+            push_pawn_moves(list, shifted(to, 2 * back), to, false);
+        }
+        for (captures, offset) in [
+            (east & capture_targets, east_back),
+            (west & capture_targets, west_back),
+        ] {
+            for to in captures {
+                push_pawn_moves(list, shifted(to, offset), to, true);
+            }
+        }
 
-pub fn bitscan_forwards(bb: u64) -> u64 {
-    bb.trailing_zeros() as u64
-}
+        if let Some(ep) = self.en_passant {
+            if capture_targets.contains(Square::from_index(ep as usize ^ 8)) {
+                for from in lookup.pawn_attacks[us.opposite() as usize][ep as usize] & pawns {
+                    list.push(Move::new(from, ep, MoveFlag::EnPassant));
+                }
+            }
+        }
+    }
 
-pub fn bitscan_backwards(bb: &u64) -> u64 {
-    bb.leading_zeros() as u64
-}
+    // Whether a pseudo-legal move from the staged generators keeps our king safe
+    pub fn is_legal<S: SliderAttacks>(&self, lookup: &LookUp<S>, mv: Move) -> bool {
+        let us = self.active_color;
+        let them = us.opposite();
+        let from = mv.from();
+        let to = mv.to();
+        let occupancy = self.occupancy();
+        let king = self.bb(PieceType::King, us).lsb();
+
+        if from == king {
+            // Castling paths are already checked for attacks by the generator
+            return mv.is_castle()
+                || self
+                    .attackers_by(lookup, to, occupancy ^ from.bitboard(), them)
+                    .is_empty();
+        }
 
-pub fn bitscan_forwards_with_reset(bb: u64) -> u64 {
-    bb & (bb - 1)
+        if mv.is_en_passant() {
+            return self.en_passant_is_legal(&lookup.sliders, from, to);
+        }
+        let checkers = self.attackers_by(lookup, king, occupancy, them);
+        if checkers.more_than_one() {
+            return false;
+        }
+        if !checkers.is_empty()
+            && !(checkers | lookup.between[king as usize][checkers.lsb() as usize]).contains(to)
+        {
+            return false;
+        }
+        !self.pinned(lookup, us).contains(from)
+            || lookup.line[king as usize][from as usize].contains(to)
+    }
 }
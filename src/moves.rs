@@ -0,0 +1,225 @@
+use num_traits::FromPrimitive;
+
+use crate::bitboards::{PieceType, Square};
+
+pub const MAX_MOVES: usize = 256;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, num_derive::FromPrimitive)]
+pub enum MoveFlag {
+    Quiet = 0,
+    DoublePush = 1,
+    KingCastle = 2,
+    QueenCastle = 3,
+    Capture = 4,
+    EnPassant = 5,
+    KnightPromotion = 8,
+    BishopPromotion = 9,
+    RookPromotion = 10,
+    QueenPromotion = 11,
+    KnightPromotionCapture = 12,
+    BishopPromotionCapture = 13,
+    RookPromotionCapture = 14,
+    QueenPromotionCapture = 15,
+}
+
+// bits 0-5: from square, bits 6-11: to square, bits 12-15: MoveFlag
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Move(u16);
+
+const CAPTURE_BIT: u16 = 4;
+const PROMOTION_BIT: u16 = 8;
+
+impl Move {
+    pub const NULL: Move = Move(0);
+
+    pub fn new(from: Square, to: Square, flag: MoveFlag) -> Move {
+        Move(from as u16 | (to as u16) << 6 | (flag as u16) << 12)
+    }
+
+    pub fn new_promotion(from: Square, to: Square, piece: PieceType, capture: bool) -> Move {
+        let mut flag = PROMOTION_BIT
+            | match piece {
+                PieceType::Knight => 0,
+                PieceType::Bishop => 1,
+                PieceType::Rook => 2,
+                PieceType::Queen => 3,
+                _ => panic!("cannot promote to {:?}", piece),
+            };
+        if capture {
+            flag |= CAPTURE_BIT;
+        }
+        Move(from as u16 | (to as u16) << 6 | flag << 12)
+    }
+
+    pub fn from(self) -> Square {
+        Square::from_index((self.0 & 0x3f) as usize)
+    }
+
+    pub fn to(self) -> Square {
+        Square::from_index(((self.0 >> 6) & 0x3f) as usize)
+    }
+
+    pub fn flag(self) -> MoveFlag {
+        MoveFlag::from_u16(self.0 >> 12).unwrap()
+    }
+
+    pub fn is_null(self) -> bool {
+        self == Move::NULL
+    }
+
+    pub fn is_capture(self) -> bool {
+        (self.0 >> 12) & CAPTURE_BIT != 0
+    }
+
+    pub fn is_double_push(self) -> bool {
+        self.flag() == MoveFlag::DoublePush
+    }
+
+    pub fn is_en_passant(self) -> bool {
+        self.flag() == MoveFlag::EnPassant
+    }
+
+    pub fn is_castle(self) -> bool {
+        matches!(self.flag(), MoveFlag::KingCastle | MoveFlag::QueenCastle)
+    }
+
+    pub fn is_promotion(self) -> bool {
+        (self.0 >> 12) & PROMOTION_BIT != 0
+    }
+
+    pub fn promotion(self) -> Option<PieceType> {
+        if !self.is_promotion() {
+            return None;
+        }
+        match (self.0 >> 12) & 3 {
+            0 => Some(PieceType::Knight),
+            1 => Some(PieceType::Bishop),
+            2 => Some(PieceType::Rook),
+            _ => Some(PieceType::Queen),
+        }
+    }
+
+    // Castling is stored as king-takes-rook so Chess960 castling is unambiguous,
+    // these give where the king and rook actually land
+    pub fn castling_king_to(self) -> Square {
+        let file = match self.flag() {
+            MoveFlag::KingCastle => 6,
+            _ => 2,
+        };
+        Square::from_coords(file, self.from().rank()).unwrap()
+    }
+
+    pub fn castling_rook_to(self) -> Square {
+        let file = match self.flag() {
+            MoveFlag::KingCastle => 5,
+            _ => 3,
+        };
+        Square::from_coords(file, self.from().rank()).unwrap()
+    }
+
+    // Long algebraic notation as UCI sends it, castling as the king's two-square step
+    pub fn to_uci(self) -> String {
+        self.format_uci(false)
+    }
+
+    // UCI_Chess960 notation, castling as king-takes-rook
+    pub fn to_uci_chess960(self) -> String {
+        self.format_uci(true)
+    }
+
+    fn format_uci(self, chess960: bool) -> String {
+        if self.is_null() {
+            return "0000".to_string();
+        }
+        let to = if self.is_castle() && !chess960 {
+            self.castling_king_to()
+        } else {
+            self.to()
+        };
+        let mut uci = format!("{}{}", self.from(), to);
+        match self.promotion() {
+            Some(PieceType::Knight) => uci.push('n'),
+            Some(PieceType::Bishop) => uci.push('b'),
+            Some(PieceType::Rook) => uci.push('r'),
+            Some(_) => uci.push('q'),
+            None => (),
+        }
+        uci
+    }
+
+    pub fn raw(self) -> u16 {
+        self.0
+    }
+}
+
+impl std::fmt::Display for Move {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.to_uci())
+    }
+}
+
+impl std::fmt::Debug for Move {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "Move({}, {:?})", self, self.flag())
+    }
+}
+
+// Fixed capacity list so move generation never touches the heap
+pub struct MoveList {
+    moves: [Move; MAX_MOVES],
+    len: usize,
+}
+
+impl MoveList {
+    pub fn new() -> MoveList {
+        MoveList {
+            moves: [Move::NULL; MAX_MOVES],
+            len: 0,
+        }
+    }
+
+    #[inline(always)]
+    pub fn push(&mut self, mv: Move) {
+        self.moves[self.len] = mv;
+        self.len += 1;
+    }
+
+    pub fn clear(&mut self) {
+        self.len = 0;
+    }
+}
+
+impl Default for MoveList {
+    fn default() -> MoveList {
+        MoveList::new()
+    }
+}
+
+impl std::ops::Deref for MoveList {
+    type Target = [Move];
+
+    fn deref(&self) -> &[Move] {
+        &self.moves[..self.len]
+    }
+}
+
+impl std::ops::DerefMut for MoveList {
+    fn deref_mut(&mut self) -> &mut [Move] {
+        &mut self.moves[..self.len]
+    }
+}
+
+impl<'a> IntoIterator for &'a MoveList {
+    type Item = &'a Move;
+    type IntoIter = std::slice::Iter<'a, Move>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl std::fmt::Debug for MoveList {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
@@ -0,0 +1,265 @@
+use crate::bitboards::{Bitboard, Board, Color, PieceType};
+use crate::movegen::LookUp;
+use crate::sliders::SliderAttacks;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameResult {
+    WhiteWins,
+    BlackWins,
+    Draw,
+}
+
+impl std::fmt::Display for GameResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let result = match self {
+            GameResult::WhiteWins => "1-0",
+            GameResult::BlackWins => "0-1",
+            GameResult::Draw => "1/2-1/2",
+        };
+        write!(f, "{}", result)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Checkmate { winner: Color },
+    Stalemate,
+    InsufficientMaterial,
+    // 150 plies without a capture or pawn move, ends the game without a claim
+    SeventyFiveMoveRule,
+    // Same position five times, ends the game without a claim
+    FivefoldRepetition,
+    // 100 plies without a capture or pawn move, a draw once claimed
+    FiftyMoveRule,
+    // Same position three times, a draw once claimed
+    ThreefoldRepetition,
+}
+
+impl Outcome {
+    pub fn result(self) -> GameResult {
+        match self {
+            Outcome::Checkmate {
+                winner: Color::White,
+            } => GameResult::WhiteWins,
+            Outcome::Checkmate {
+                winner: Color::Black,
+            } => GameResult::BlackWins,
+            _ => GameResult::Draw,
+        }
+    }
+
+    // Draws a player has to ask for, the game carries on if nobody does
+    pub fn is_claimable(self) -> bool {
+        matches!(self, Outcome::FiftyMoveRule | Outcome::ThreefoldRepetition)
+    }
+}
+
+impl Board {
+    // Why the game is over, or can be claimed drawn, in this position. `history` holds
+    // the keys of every earlier position in the game, oldest first. Game-ending
+    // outcomes come before claimable draws, and mate on the last allowed move counts.
+    pub fn outcome<S: SliderAttacks>(
+        &self,
+        lookup: &LookUp<S>,
+        history: &[u64],
+    ) -> Option<Outcome> {
+        if self.legal_moves(lookup).is_empty() {
+            return Some(if self.in_check(lookup) {
+                Outcome::Checkmate {
+                    winner: self.active_color.opposite(),
+                }
+            } else {
+                Outcome::Stalemate
+            });
+        }
+        if self.is_insufficient_material() {
+            return Some(Outcome::InsufficientMaterial);
+        }
+
+        let repetitions = self.repetitions(history);
+        if self.halfmove_clock >= 150 {
+            Some(Outcome::SeventyFiveMoveRule)
+        } else if repetitions >= 5 {
+            Some(Outcome::FivefoldRepetition)
+        } else if self.halfmove_clock >= 100 {
+            Some(Outcome::FiftyMoveRule)
+        } else if repetitions >= 3 {
+            Some(Outcome::ThreefoldRepetition)
+        } else {
+            None
+        }
+    }
+
+    // How many times this position has occurred, counting itself. Only positions
+    // since the last capture or pawn move with the same side to move can match.
+    pub fn repetitions(&self, history: &[u64]) -> usize {
+        let reversible = (self.halfmove_clock as usize).min(history.len());
+        1 + history[history.len() - reversible..]
+            .iter()
+            .rev()
+            .skip(1)
+            .step_by(2)
+            .filter(|&&key| key == self.key)
+            .count()
+    }
+
+    // Neither side can ever mate: bare kings, a single minor piece, or only
+    // bishops that all stand on squares of one colour
+    pub fn is_insufficient_material(&self) -> bool {
+        let heavy = self.pieces[PieceType::Pawn as usize]
+            | self.pieces[PieceType::Rook as usize]
+            | self.pieces[PieceType::Queen as usize];
+        if !heavy.is_empty() {
+            return false;
+        }
+        let bishops = self.pieces[PieceType::Bishop as usize];
+        let minors = bishops | self.pieces[PieceType::Knight as usize];
+        if minors.popcount() <= 1 {
+            return true;
+        }
+        minors == bishops
+            && ((bishops & Bitboard::LIGHT_SQUARES).is_empty()
+                || (bishops & Bitboard::DARK_SQUARES).is_empty())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::Game;
+
+    // Each position is played out from the FEN by the UCI moves, in order
+    const CASES: &[(&str, &str, &str, Option<Outcome>)] = &[
+        (
+            "fool's mate",
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "f2f3 e7e5 g2g4 d8h4",
+            Some(Outcome::Checkmate {
+                winner: Color::Black,
+            }),
+        ),
+        (
+            "back rank mate",
+            "k7/8/1K6/8/8/8/8/7R w - - 0 1",
+            "h1h8",
+            Some(Outcome::Checkmate {
+                winner: Color::White,
+            }),
+        ),
+        (
+            "stalemate",
+            "7k/5Q2/6K1/8/8/8/8/8 b - - 0 1",
+            "",
+            Some(Outcome::Stalemate),
+        ),
+        (
+            "bare kings",
+            "8/8/4k3/8/8/3K4/8/8 w - - 0 1",
+            "",
+            Some(Outcome::InsufficientMaterial),
+        ),
+        (
+            "king and knight",
+            "8/8/4k3/8/8/3K4/8/6N1 w - - 0 1",
+            "",
+            Some(Outcome::InsufficientMaterial),
+        ),
+        (
+            "bishops on one colour",
+            "8/8/4kb2/8/8/3K4/8/2B5 w - - 0 1",
+            "",
+            Some(Outcome::InsufficientMaterial),
+        ),
+        (
+            "bishops on both colours",
+            "8/8/4k1b1/8/8/3K4/8/2B5 w - - 0 1",
+            "",
+            None,
+        ),
+        ("two knights", "8/8/4k3/8/8/3K4/8/1N4N1 w - - 0 1", "", None),
+        (
+            "one ply short of fifty moves",
+            "8/8/4k3/8/8/3K4/8/R7 w - - 99 80",
+            "",
+            None,
+        ),
+        (
+            "fifty moves",
+            "8/8/4k3/8/8/3K4/8/R7 w - - 99 80",
+            "a1a2",
+            Some(Outcome::FiftyMoveRule),
+        ),
+        (
+            "seventy-five moves",
+            "8/8/4k3/8/8/3K4/8/R7 w - - 149 100",
+            "a1a2",
+            Some(Outcome::SeventyFiveMoveRule),
+        ),
+        (
+            "mate on the seventy-fifth move",
+            "k7/8/1K6/8/8/8/8/7R w - - 149 100",
+            "h1h8",
+            Some(Outcome::Checkmate {
+                winner: Color::White,
+            }),
+        ),
+        // Both clocks stop at u16::MAX instead of overflowing
+        (
+            "clocks at their limit",
+            "4k3/8/8/8/8/8/8/4K2R w K - 65535 65535",
+            "h1h2 e8d8",
+            Some(Outcome::SeventyFiveMoveRule),
+        ),
+        (
+            "twofold repetition",
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "g1f3 g8f6 f3g1 f6g8",
+            None,
+        ),
+        (
+            "threefold repetition",
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "g1f3 g8f6 f3g1 f6g8 g1f3 g8f6 f3g1 f6g8",
+            Some(Outcome::ThreefoldRepetition),
+        ),
+        // The en passant square after e5 can't be taken, so it's the same position
+        (
+            "threefold after a double push",
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "e2e4 e7e5 g1f3 g8f6 f3g1 f6g8 g1f3 g8f6 f3g1 f6g8",
+            Some(Outcome::ThreefoldRepetition),
+        ),
+        // Here it can, so the position after e4 isn't repeated
+        (
+            "capturable en passant square",
+            "4k3/8/8/8/3p4/8/4P3/4K3 w - - 0 1",
+            "e2e4 e8d8 e1d1 d8e8 d1e1 e8d8 e1d1 d8e8 d1e1",
+            None,
+        ),
+        (
+            "fivefold repetition",
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "g1f3 g8f6 f3g1 f6g8 g1f3 g8f6 f3g1 f6g8 \
+             g1f3 g8f6 f3g1 f6g8 g1f3 g8f6 f3g1 f6g8",
+            Some(Outcome::FivefoldRepetition),
+        ),
+    ];
+
+    #[test]
+    fn outcomes() {
+        let lookup = LookUp::init();
+        let mut failures = Vec::new();
+        for (name, fen, moves, expected) in CASES {
+            let mut game = Game::from_fen(fen).unwrap();
+            for uci in moves.split_ascii_whitespace() {
+                let mv = game.board().parse_uci_move(&lookup, uci).unwrap();
+                game.push(mv);
+            }
+            let outcome = game.outcome(&lookup);
+            if outcome != *expected {
+                failures.push(format!("{}: {:?} (expected {:?})", name, outcome, expected));
+            }
+        }
+        assert!(failures.is_empty(), "{}", failures.join("\n"));
+    }
+}
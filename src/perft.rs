@@ -0,0 +1,197 @@
+use crate::bitboards::Board;
+use crate::movegen::LookUp;
+use crate::moves::Move;
+use crate::sliders::SliderAttacks;
+
+// Leaf nodes at depth, counting the last ply straight from the move list
+pub fn perft<S: SliderAttacks>(board: &mut Board, lookup: &LookUp<S>, depth: u32) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+    let moves = board.legal_moves(lookup);
+    if depth == 1 {
+        return moves.len() as u64;
+    }
+
+    let mut nodes = 0;
+    for &mv in moves.iter() {
+        let undo = board.make_move(mv);
+        nodes += perft(board, lookup, depth - 1);
+        board.unmake_move(mv, &undo);
+    }
+    nodes
+}
+
+// perft split by root move. Depth 0 has no root moves to split by, so it's empty
+// even though perft counts the position itself there.
+pub fn divide<S: SliderAttacks>(
+    board: &mut Board,
+    lookup: &LookUp<S>,
+    depth: u32,
+) -> Vec<(Move, u64)> {
+    if depth == 0 {
+        return Vec::new();
+    }
+    let moves = board.legal_moves(lookup);
+    let mut result = Vec::with_capacity(moves.len());
+    for &mv in moves.iter() {
+        let undo = board.make_move(mv);
+        result.push((mv, perft(board, lookup, depth - 1)));
+        board.unmake_move(mv, &undo);
+    }
+    result
+}
+
+pub struct PerftCase {
+    pub name: &'static str,
+    pub fen: &'static str,
+    // (depth, nodes) pairs
+    pub counts: &'static [(u32, u64)],
+}
+
+// Reference positions from the chessprogramming wiki, the TalkChess perft suite
+// and Reinhard Scharnagl's Chess960 perft results
+pub const SUITE: &[PerftCase] = &[
+    PerftCase {
+        name: "start position",
+        fen: "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        counts: &[(1, 20), (2, 400), (3, 8902), (4, 197281), (5, 4865609)],
+    },
+    PerftCase {
+        name: "kiwipete",
+        fen: "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        counts: &[(1, 48), (2, 2039), (3, 97862), (4, 4085603)],
+    },
+    PerftCase {
+        name: "position 3",
+        fen: "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+        counts: &[(1, 14), (2, 191), (3, 2812), (4, 43238), (5, 674624)],
+    },
+    PerftCase {
+        name: "position 4",
+        fen: "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+        counts: &[(1, 6), (2, 264), (3, 9467), (4, 422333)],
+    },
+    PerftCase {
+        name: "position 4 mirrored",
+        fen: "r2q1rk1/pP1p2pp/Q4n2/bbp1p3/Np6/1B3NBn/pPPP1PPP/R3K2R b KQ - 0 1",
+        counts: &[(1, 6), (2, 264), (3, 9467), (4, 422333)],
+    },
+    PerftCase {
+        name: "position 5",
+        fen: "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+        counts: &[(1, 44), (2, 1486), (3, 62379), (4, 2103487)],
+    },
+    PerftCase {
+        name: "position 6",
+        fen: "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10",
+        counts: &[(1, 46), (2, 2079), (3, 89890), (4, 3894594)],
+    },
+    PerftCase {
+        name: "illegal en passant 1",
+        fen: "3k4/3p4/8/K1P4r/8/8/8/8 b - - 0 1",
+        counts: &[(6, 1134888)],
+    },
+    PerftCase {
+        name: "illegal en passant 2",
+        fen: "8/8/4k3/8/2p5/8/B2P2K1/8 w - - 0 1",
+        counts: &[(6, 1015133)],
+    },
+    PerftCase {
+        name: "en passant gives check",
+        fen: "8/8/1k6/2b5/2pP4/8/5K2/8 b - d3 0 1",
+        counts: &[(6, 1440467)],
+    },
+    PerftCase {
+        name: "short castling gives check",
+        fen: "5k2/8/8/8/8/8/8/4K2R w K - 0 1",
+        counts: &[(6, 661072)],
+    },
+    PerftCase {
+        name: "long castling gives check",
+        fen: "3k4/8/8/8/8/8/8/R3K3 w Q - 0 1",
+        counts: &[(6, 803711)],
+    },
+    PerftCase {
+        name: "castling rights",
+        fen: "r3k2r/1b4bq/8/8/8/8/7B/R3K2R w KQkq - 0 1",
+        counts: &[(4, 1274206)],
+    },
+    PerftCase {
+        name: "castling prevented",
+        fen: "r3k2r/8/3Q4/8/8/5q2/8/R3K2R b KQkq - 0 1",
+        counts: &[(4, 1720476)],
+    },
+    PerftCase {
+        name: "promote out of check",
+        fen: "2K2r2/4P3/8/8/8/8/8/3k4 w - - 0 1",
+        counts: &[(6, 3821001)],
+    },
+    PerftCase {
+        name: "discovered check",
+        fen: "8/8/1P2K3/8/2n5/1q6/8/5k2 b - - 0 1",
+        counts: &[(5, 1004658)],
+    },
+    PerftCase {
+        name: "promote to give check",
+        fen: "4k3/1P6/8/8/8/8/K7/8 w - - 0 1",
+        counts: &[(6, 217342)],
+    },
+    PerftCase {
+        name: "underpromote to give check",
+        fen: "8/P1k5/K7/8/8/8/8/8 w - - 0 1",
+        counts: &[(6, 92683)],
+    },
+    PerftCase {
+        name: "self stalemate",
+        fen: "K1k5/8/P7/8/8/8/8/8 w - - 0 1",
+        counts: &[(6, 2217)],
+    },
+    PerftCase {
+        name: "stalemate and checkmate 1",
+        fen: "8/k1P5/8/1K6/8/8/8/8 w - - 0 1",
+        counts: &[(7, 567584)],
+    },
+    PerftCase {
+        name: "stalemate and checkmate 2",
+        fen: "8/8/2k5/5q2/5n2/8/5K2/8 b - - 0 1",
+        counts: &[(4, 23527)],
+    },
+    // The pawn on c5 checks along the diagonal through d6, which exd6 doesn't block.
+    // Counted with a separate brute-force generator.
+    PerftCase {
+        name: "en passant in pawn check",
+        fen: "8/8/8/2ppP3/1K6/8/8/7k w - d6 0 1",
+        counts: &[(1, 7), (2, 34), (4, 1391), (6, 69496)],
+    },
+    PerftCase {
+        name: "chess960 1",
+        fen: "bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9",
+        counts: &[(1, 21), (2, 528), (3, 12189), (4, 326672)],
+    },
+    PerftCase {
+        name: "chess960 2",
+        fen: "2nnrbkr/p1qppppp/8/1ppb4/6PP/3PP3/PPP2P2/BQNNRBKR w HEhe - 1 9",
+        counts: &[(1, 21), (2, 807), (3, 18002), (4, 667366)],
+    },
+    PerftCase {
+        name: "chess960 3",
+        fen: "b1q1rrkb/pppppppp/3nn3/8/P7/1PPP4/4PPPP/BQNNRKRB w GE - 1 9",
+        counts: &[(1, 20), (2, 479), (3, 10471), (4, 273318)],
+    },
+    PerftCase {
+        name: "chess960 4",
+        fen: "1nbbnrkr/p1p1ppp1/3p4/1p3P1p/3Pq2P/8/PPP1P1P1/QNBBNRKR w HFhf - 0 9",
+        counts: &[(1, 28), (2, 1120), (3, 31058), (4, 1171749)],
+    },
+    PerftCase {
+        name: "chess960 5",
+        fen: "qnbnr1kr/ppp1b1pp/4p3/3p1p2/8/2NPP3/PPP1BPPP/QNB1R1KR w HEhe - 1 9",
+        counts: &[(1, 29), (2, 899), (3, 26578), (4, 824055)],
+    },
+    PerftCase {
+        name: "chess960 6",
+        fen: "q1bnrkr1/ppppp2p/2n2p2/4b1p1/2NP4/8/PPP1PPPP/QNB1RRKB w ge - 1 9",
+        counts: &[(1, 30), (2, 860), (3, 24566), (4, 732757)],
+    },
+];
@@ -0,0 +1,132 @@
+use std::arch::x86_64::_pext_u64;
+
+use crate::bitboards::{Bitboard, Square};
+use crate::magics::{pack, per_square};
+use crate::movegen::{bishop_attacks, bishop_mask, rook_attacks, rook_mask};
+use crate::sliders::SliderAttacks;
+
+// Slider attacks indexed by pulling the relevant occupancy bits together with
+// BMI2's PEXT instead of hashing them with a magic. Every index is used, so each
+// square needs exactly 1 << popcount(mask) slots and there's nothing to search for.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PextEntry {
+    pub mask: Bitboard,
+    pub offset: usize,
+}
+
+pub static BISHOP_ENTRIES: [PextEntry; 64] = build_entries(true);
+pub static ROOK_ENTRIES: [PextEntry; 64] = build_entries(false);
+
+const BISHOP_TABLE_SIZE: usize = table_size(true);
+const ROOK_TABLE_SIZE: usize = table_size(false);
+pub static BISHOP_TABLE: [Bitboard; BISHOP_TABLE_SIZE] =
+    pack(&per_square!(build_square, slots, true));
+pub static ROOK_TABLE: [Bitboard; ROOK_TABLE_SIZE] = pack(&per_square!(build_square, slots, false));
+
+// Slider attacks through the PEXT tables. Only handed out where the CPU has BMI2.
+#[derive(Debug, Clone, Copy)]
+pub struct Pext {
+    _private: (),
+}
+
+impl Pext {
+    pub fn detect() -> Option<Pext> {
+        available().then_some(Pext { _private: () })
+    }
+}
+
+impl SliderAttacks for Pext {
+    #[inline(always)]
+    fn bishop_moves(&self, sq: Square, occupancy: Bitboard) -> Bitboard {
+        // Safe, a Pext only exists when the CPU has BMI2
+        unsafe { bishop_moves(sq, occupancy) }
+    }
+
+    #[inline(always)]
+    fn rook_moves(&self, sq: Square, occupancy: Bitboard) -> Bitboard {
+        // Safe, a Pext only exists when the CPU has BMI2
+        unsafe { rook_moves(sq, occupancy) }
+    }
+}
+
+pub fn available() -> bool {
+    is_x86_feature_detected!("bmi2")
+}
+
+/// # Safety
+/// The CPU must support BMI2, check available() first.
+#[target_feature(enable = "bmi2")]
+#[inline]
+pub unsafe fn bishop_moves(sq: Square, occupancy: Bitboard) -> Bitboard {
+    let entry = &BISHOP_ENTRIES[sq as usize];
+    BISHOP_TABLE[entry.offset + _pext_u64(occupancy.0, entry.mask.0) as usize]
+}
+
+/// # Safety
+/// The CPU must support BMI2, check available() first.
+#[target_feature(enable = "bmi2")]
+#[inline]
+pub unsafe fn rook_moves(sq: Square, occupancy: Bitboard) -> Bitboard {
+    let entry = &ROOK_ENTRIES[sq as usize];
+    ROOK_TABLE[entry.offset + _pext_u64(occupancy.0, entry.mask.0) as usize]
+}
+
+const fn mask(sq: usize, bishop: bool) -> Bitboard {
+    if bishop {
+        bishop_mask(Square::ALL[sq])
+    } else {
+        rook_mask(Square::ALL[sq])
+    }
+}
+
+const fn slots(sq: usize, bishop: bool) -> usize {
+    1 << mask(sq, bishop).0.count_ones()
+}
+
+const fn table_size(bishop: bool) -> usize {
+    let mut size = 0;
+    let mut sq = 0;
+    while sq < 64 {
+        size += slots(sq, bishop);
+        sq += 1;
+    }
+    size
+}
+
+const fn build_entries(bishop: bool) -> [PextEntry; 64] {
+    let mut entries = [PextEntry {
+        mask: Bitboard::EMPTY,
+        offset: 0,
+    }; 64];
+    let mut offset = 0;
+    let mut sq = 0;
+    while sq < 64 {
+        entries[sq] = PextEntry {
+            mask: mask(sq, bishop),
+            offset,
+        };
+        offset += slots(sq, bishop);
+        sq += 1;
+    }
+    entries
+}
+
+// The carry-rippler walk counts up through the subsets of mask in the bit positions
+// of mask, so the i-th occupancy it visits is the one PEXT maps to i
+const fn build_square<const SIZE: usize>(sq: usize, bishop: bool) -> [Bitboard; SIZE] {
+    let mask = mask(sq, bishop).0;
+    let mut table = [Bitboard::EMPTY; SIZE];
+    let mut occupancy = 0u64;
+    let mut index = 0;
+    while index < SIZE {
+        table[index] = if bishop {
+            bishop_attacks(Square::ALL[sq], Bitboard(occupancy))
+        } else {
+            rook_attacks(Square::ALL[sq], Bitboard(occupancy))
+        };
+        occupancy = occupancy.wrapping_sub(mask) & mask;
+        index += 1;
+    }
+    table
+}
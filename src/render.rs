@@ -0,0 +1,160 @@
+use std::fmt::Write;
+
+use crate::bitboards::{Bitboard, Board, Color, Piece, PieceType, Square};
+use crate::movegen::LookUp;
+use crate::moves::Move;
+use crate::sliders::SliderAttacks;
+
+#[derive(Debug, Clone, Copy)]
+pub struct RenderOptions {
+    // Chess figurines instead of FEN letters
+    pub unicode: bool,
+    pub coordinates: bool,
+    // Black's point of view, h1 in the top left
+    pub flipped: bool,
+    // Coloured squares, needed for the last move and check highlights
+    pub ansi_colors: bool,
+    pub last_move: Option<Move>,
+    pub highlight_check: bool,
+    // Extra squares to mark, e.g. the attacked_squares of one side
+    pub overlay: Bitboard,
+    // FEN and Zobrist key under the board
+    pub state: bool,
+}
+
+impl Default for RenderOptions {
+    fn default() -> RenderOptions {
+        RenderOptions {
+            unicode: false,
+            coordinates: true,
+            flipped: false,
+            ansi_colors: false,
+            last_move: None,
+            highlight_check: true,
+            overlay: Bitboard::EMPTY,
+            state: true,
+        }
+    }
+}
+
+// 256-colour palette backgrounds
+const LIGHT_SQUARE: u8 = 180;
+const DARK_SQUARE: u8 = 137;
+const LAST_MOVE: u8 = 149;
+const CHECK: u8 = 167;
+const OVERLAY: u8 = 74;
+
+fn figurine(piece: Piece) -> char {
+    match (piece.color, piece.r#type) {
+        (Color::White, PieceType::Pawn) => '♙',
+        (Color::White, PieceType::Bishop) => '♗',
+        (Color::White, PieceType::Knight) => '♘',
+        (Color::White, PieceType::Rook) => '♖',
+        (Color::White, PieceType::Queen) => '♕',
+        (Color::White, PieceType::King) => '♔',
+        (Color::Black, PieceType::Pawn) => '♟',
+        (Color::Black, PieceType::Bishop) => '♝',
+        (Color::Black, PieceType::Knight) => '♞',
+        (Color::Black, PieceType::Rook) => '♜',
+        (Color::Black, PieceType::Queen) => '♛',
+        (Color::Black, PieceType::King) => '♚',
+    }
+}
+
+impl Board {
+    pub fn render<S: SliderAttacks>(&self, lookup: &LookUp<S>, options: &RenderOptions) -> String {
+        let check = if options.highlight_check && self.in_check(lookup) {
+            self.king_square(self.active_color).bitboard()
+        } else {
+            Bitboard::EMPTY
+        };
+        self.render_with(options, check)
+    }
+
+    fn render_with(&self, options: &RenderOptions, check: Bitboard) -> String {
+        let last_move = options.last_move.map_or(Bitboard::EMPTY, |mv| {
+            mv.from().bitboard() | mv.to().bitboard()
+        });
+        let (ranks, files): (Vec<u8>, Vec<u8>) = if options.flipped {
+            ((0..8).collect(), (0..8).rev().collect())
+        } else {
+            ((0..8).rev().collect(), (0..8).collect())
+        };
+
+        let mut out = String::new();
+        for &rank in &ranks {
+            if options.coordinates {
+                write!(out, "{} ", rank + 1).unwrap();
+            }
+            for &file in &files {
+                let sq = Square::from_coords(file, rank).unwrap();
+                let piece = self.squares[sq as usize];
+                let symbol = match piece {
+                    Some(piece) if options.unicode => figurine(piece),
+                    Some(piece) => piece.to_char(),
+                    // Without colours the overlay can only show on empty squares
+                    None if !options.ansi_colors && options.overlay.contains(sq) => 'x',
+                    None if !options.ansi_colors => '.',
+                    None => ' ',
+                };
+                if !options.ansi_colors {
+                    write!(out, " {}", symbol).unwrap();
+                    continue;
+                }
+
+                let background = if check.contains(sq) {
+                    CHECK
+                } else if last_move.contains(sq) {
+                    LAST_MOVE
+                } else if options.overlay.contains(sq) {
+                    OVERLAY
+                } else if Bitboard::LIGHT_SQUARES.contains(sq) {
+                    LIGHT_SQUARE
+                } else {
+                    DARK_SQUARE
+                };
+                let foreground = match piece.map(|piece| piece.color) {
+                    Some(Color::Black) => 30,
+                    _ => 97,
+                };
+                write!(
+                    out,
+                    "\x1b[48;5;{}m\x1b[1;{}m {} \x1b[0m",
+                    background, foreground, symbol
+                )
+                .unwrap();
+            }
+            out.push('\n');
+        }
+
+        if options.coordinates {
+            out.push_str("  ");
+            for &file in &files {
+                let label = (b'a' + file) as char;
+                if options.ansi_colors {
+                    write!(out, " {} ", label).unwrap();
+                } else {
+                    write!(out, " {}", label).unwrap();
+                }
+            }
+            out.push('\n');
+        }
+        if options.state {
+            writeln!(out, "\nFEN: {}", self.to_fen()).unwrap();
+            writeln!(out, "Key: {:016x}", self.key).unwrap();
+        }
+        out
+    }
+}
+
+// Plain text with coordinates and state. Use Board::render for the rest,
+// check highlighting needs the attack tables.
+impl std::fmt::Display for Board {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            self.render_with(&RenderOptions::default(), Bitboard::EMPTY)
+        )
+    }
+}
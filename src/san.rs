@@ -0,0 +1,392 @@
+use crate::bitboards::{Board, Color, Piece, PieceType, Square};
+use crate::movegen::LookUp;
+use crate::moves::{Move, MoveFlag};
+use crate::sliders::SliderAttacks;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SanError {
+    // Not shaped like a move at all
+    Malformed { san: String },
+    // Reads fine but no legal move matches it
+    Illegal { san: String },
+    // More than one legal move matches it
+    Ambiguous { san: String, candidates: Vec<Move> },
+}
+
+impl std::fmt::Display for SanError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            SanError::Malformed { san } => write!(f, "malformed move: {}", san),
+            SanError::Illegal { san } => write!(f, "illegal move: {}", san),
+            SanError::Ambiguous { san, candidates } => {
+                write!(f, "ambiguous move: {} could be", san)?;
+                for mv in candidates {
+                    write!(f, " {}", mv)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for SanError {}
+
+// SAN always writes pieces in upper case, whoever moves them
+fn piece_letter(r#type: PieceType) -> char {
+    Piece {
+        r#type,
+        color: Color::White,
+    }
+    .to_char()
+}
+
+impl Move {
+    // The move in Standard Algebraic Notation, it must be legal on board
+    pub fn to_san<S: SliderAttacks>(self, board: &Board, lookup: &LookUp<S>) -> String {
+        let from = self.from();
+        let to = self.to();
+        let mut san = String::new();
+        if self.is_castle() {
+            san.push_str(match self.flag() {
+                MoveFlag::KingCastle => "O-O",
+                _ => "O-O-O",
+            });
+        } else {
+            let piece = board.squares[from as usize]
+                .unwrap_or_else(|| panic!("no piece on {:?}", from))
+                .r#type;
+            if piece == PieceType::Pawn {
+                if self.is_capture() {
+                    san.push((b'a' + from.file()) as char);
+                }
+            } else {
+                san.push(piece_letter(piece));
+                // Name just enough of the origin to tell it apart from the same piece type
+                // also reaching `to`: the file if that does it, else the rank, else both
+                let others: Vec<Square> = board
+                    .legal_moves(lookup)
+                    .iter()
+                    .filter(|mv| {
+                        mv.to() == to
+                            && mv.from() != from
+                            && !mv.is_castle()
+                            && board.squares[mv.from() as usize].map(|p| p.r#type) == Some(piece)
+                    })
+                    .map(|mv| mv.from())
+                    .collect();
+                if !others.is_empty() {
+                    if others.iter().all(|sq| sq.file() != from.file()) {
+                        san.push((b'a' + from.file()) as char);
+                    } else if others.iter().all(|sq| sq.rank() != from.rank()) {
+                        san.push((b'1' + from.rank()) as char);
+                    } else {
+                        san.push_str(&from.to_string());
+                    }
+                }
+            }
+            if self.is_capture() {
+                san.push('x');
+            }
+            san.push_str(&to.to_string());
+            if let Some(promotion) = self.promotion() {
+                san.push('=');
+                san.push(piece_letter(promotion));
+            }
+        }
+
+        let mut after = board.clone();
+        after.make_move(self);
+        if after.in_check(lookup) {
+            san.push(if after.legal_moves(lookup).is_empty() {
+                '#'
+            } else {
+                '+'
+            });
+        }
+        san
+    }
+}
+
+// What a non-castling SAN string asks for: the piece, the promotion and whatever
+// part of the origin it names, then the destination
+struct SanParts {
+    piece: PieceType,
+    promotion: Option<PieceType>,
+    from_file: Option<u8>,
+    from_rank: Option<u8>,
+    to: Square,
+}
+
+// Capture and separator marks are optional, so exd6, ed6 and e5-d6 all read the same
+fn parse_san_parts(text: &str) -> Option<SanParts> {
+    let mut chars: Vec<char> = text
+        .chars()
+        .filter(|&ch| ch != 'x' && ch != '-' && ch != ':')
+        .collect();
+
+    let piece = match chars.first() {
+        Some(&ch) if "NBRQK".contains(ch) => {
+            chars.remove(0);
+            Piece::from_char(ch)?.r#type
+        }
+        _ => PieceType::Pawn,
+    };
+
+    // Promotion with or without the '=', e8=Q and e8Q alike
+    let mut promotion = None;
+    if let Some(&ch) = chars.last() {
+        if ch.is_ascii_alphabetic() {
+            let r#type = Piece::from_char(ch)?.r#type;
+            if piece != PieceType::Pawn || matches!(r#type, PieceType::Pawn | PieceType::King) {
+                return None;
+            }
+            promotion = Some(r#type);
+            chars.pop();
+            if chars.last() == Some(&'=') {
+                chars.pop();
+            }
+        }
+    }
+
+    let split = chars.len().checked_sub(2)?;
+    let to: Square = chars[split..].iter().collect::<String>().parse().ok()?;
+    let is_file = |ch: char| ('a'..='h').contains(&ch);
+    let is_rank = |ch: char| ('1'..='8').contains(&ch);
+    let (from_file, from_rank) = match chars[..split] {
+        [] => (None, None),
+        [file] if is_file(file) => (Some(file as u8 - b'a'), None),
+        [rank] if is_rank(rank) => (None, Some(rank as u8 - b'1')),
+        [file, rank] if is_file(file) && is_rank(rank) => {
+            (Some(file as u8 - b'a'), Some(rank as u8 - b'1'))
+        }
+        _ => return None,
+    };
+
+    Some(SanParts {
+        piece,
+        promotion,
+        from_file,
+        from_rank,
+        to,
+    })
+}
+
+impl Board {
+    // Reads a move in Standard Algebraic Notation. Check, mate and annotation marks
+    // are ignored, as are a trailing "e.p." and a redundant disambiguation.
+    pub fn parse_san<S: SliderAttacks>(
+        &self,
+        lookup: &LookUp<S>,
+        san: &str,
+    ) -> Result<Move, SanError> {
+        let text = san.trim().trim_end_matches(['+', '#', '!', '?']);
+        let text = text.strip_suffix("e.p.").unwrap_or(text).trim_end();
+        let legal = self.legal_moves(lookup);
+
+        let castle = match text {
+            "O-O" | "0-0" => Some(MoveFlag::KingCastle),
+            "O-O-O" | "0-0-0" => Some(MoveFlag::QueenCastle),
+            _ => None,
+        };
+        let candidates: Vec<Move> = match castle {
+            Some(flag) => legal
+                .iter()
+                .filter(|mv| mv.flag() == flag)
+                .copied()
+                .collect(),
+            None => {
+                let parts = parse_san_parts(text).ok_or_else(|| SanError::Malformed {
+                    san: san.to_string(),
+                })?;
+                legal
+                    .iter()
+                    .filter(|mv| {
+                        let from = mv.from();
+                        !mv.is_castle()
+                            && mv.to() == parts.to
+                            && self.squares[from as usize].map(|p| p.r#type) == Some(parts.piece)
+                            && parts.from_file.is_none_or(|file| from.file() == file)
+                            && parts.from_rank.is_none_or(|rank| from.rank() == rank)
+                            && mv.promotion() == parts.promotion
+                    })
+                    .copied()
+                    .collect()
+            }
+        };
+
+        match candidates[..] {
+            [mv] => Ok(mv),
+            [] => Err(SanError::Illegal {
+                san: san.to_string(),
+            }),
+            _ => Err(SanError::Ambiguous {
+                san: san.to_string(),
+                candidates,
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::perft::SUITE;
+
+    // Inputs parse_san must forgive, and ones it must turn down. Each expects the
+    // move in UCI notation or the error message.
+    const CASES: &[(&str, &str, &str, Result<&str, &str>)] = &[
+        (
+            "promotion with the '='",
+            "8/4P1k1/8/8/8/8/8/4K3 w - - 0 1",
+            "e8=Q",
+            Ok("e7e8q"),
+        ),
+        (
+            "promotion without the '='",
+            "8/4P1k1/8/8/8/8/8/4K3 w - - 0 1",
+            "e8Q",
+            Ok("e7e8q"),
+        ),
+        (
+            "underpromotion with check",
+            "8/4P1k1/8/8/8/8/8/4K3 w - - 0 1",
+            "e8=N+",
+            Ok("e7e8n"),
+        ),
+        (
+            "en passant marked e.p.",
+            "4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1",
+            "exd6e.p.",
+            Ok("e5d6"),
+        ),
+        (
+            "en passant marked e.p. after a space",
+            "4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1",
+            "exd6 e.p.",
+            Ok("e5d6"),
+        ),
+        (
+            "pawn capture without the 'x'",
+            "4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1",
+            "ed6",
+            Ok("e5d6"),
+        ),
+        (
+            "castling with zeros",
+            "4k3/8/8/8/8/8/8/4K2R w K - 0 1",
+            "0-0",
+            Ok("e1g1"),
+        ),
+        (
+            "long castling with zeros",
+            "r3k3/8/8/8/8/8/8/4K3 b q - 0 1",
+            "0-0-0",
+            Ok("e8c8"),
+        ),
+        (
+            "redundant file",
+            "rnbqkbnr/ppp1pppp/8/3p4/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2",
+            "Nbd7",
+            Ok("b8d7"),
+        ),
+        (
+            "file picks one of two knights",
+            "4k3/8/8/8/8/5N2/8/1N2K3 w - - 0 1",
+            "Nbd2",
+            Ok("b1d2"),
+        ),
+        (
+            "annotations",
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "Nf3!?",
+            Ok("g1f3"),
+        ),
+        (
+            "two knights reach d2",
+            "4k3/8/8/8/8/5N2/8/1N2K3 w - - 0 1",
+            "Nd2",
+            Err("ambiguous move: Nd2 could be b1d2 f3d2"),
+        ),
+        (
+            "two rooks on the file reach a4",
+            "4k3/R7/8/8/8/8/8/R3K3 w - - 0 1",
+            "Raa4",
+            Err("ambiguous move: Raa4 could be a1a4 a7a4"),
+        ),
+        (
+            "square off the board",
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "e9",
+            Err("malformed move: e9"),
+        ),
+        (
+            "unknown piece",
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "Zf3",
+            Err("malformed move: Zf3"),
+        ),
+        (
+            "promotion to a king",
+            "8/4P1k1/8/8/8/8/8/4K3 w - - 0 1",
+            "e8=K",
+            Err("malformed move: e8=K"),
+        ),
+        (
+            "empty",
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "",
+            Err("malformed move: "),
+        ),
+        (
+            "pawn three squares",
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "e5",
+            Err("illegal move: e5"),
+        ),
+    ];
+
+    #[test]
+    fn lenient_and_bad_input() {
+        let lookup = LookUp::init();
+        let mut failures = Vec::new();
+        for &(name, fen, san, expected) in CASES {
+            let board = Board::from_fen(fen).unwrap();
+            let result = board
+                .parse_san(&lookup, san)
+                .map(|mv| mv.to_uci())
+                .map_err(|e| e.to_string());
+            if result.as_deref().map_err(String::as_str) != expected {
+                failures.push(format!("{}: {:?} (expected {:?})", name, result, expected));
+            }
+        }
+        assert!(failures.is_empty(), "{}", failures.join("\n"));
+    }
+
+    // parse_san(to_san(m)) == m for every legal move within depth
+    fn round_trip(board: &mut Board, lookup: &LookUp, depth: u32) {
+        if depth == 0 {
+            return;
+        }
+        for &mv in board.legal_moves(lookup).iter() {
+            let san = mv.to_san(board, lookup);
+            assert_eq!(
+                board.parse_san(lookup, &san),
+                Ok(mv),
+                "{} in {}",
+                san,
+                board.to_fen()
+            );
+            let undo = board.make_move(mv);
+            round_trip(board, lookup, depth - 1);
+            board.unmake_move(mv, &undo);
+        }
+    }
+
+    #[test]
+    fn round_trip_over_perft_suite() {
+        let lookup = LookUp::init();
+        for case in SUITE {
+            round_trip(&mut Board::from_fen(case.fen).unwrap(), &lookup, 2);
+        }
+    }
+}
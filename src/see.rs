@@ -0,0 +1,536 @@
+use crate::bitboards::{Bitboard, Board, PieceType, Square};
+use crate::movegen::LookUp;
+use crate::moves::Move;
+use crate::sliders::SliderAttacks;
+
+// Indexed by PieceType. The king's value never decides anything, it's never captured.
+pub const SEE_VALUES: [i32; 6] = [100, 300, 300, 500, 900, 20000];
+
+// Cheapest first, the order pieces join an exchange in
+const ATTACKER_ORDER: [PieceType; 6] = [
+    PieceType::Pawn,
+    PieceType::Knight,
+    PieceType::Bishop,
+    PieceType::Rook,
+    PieceType::Queen,
+    PieceType::King,
+];
+
+#[inline(always)]
+fn value(piece: PieceType) -> i32 {
+    SEE_VALUES[piece as usize]
+}
+
+impl Board {
+    // Material the side to move comes out with after mv and the best sequence of
+    // captures on its destination, each side recapturing with its least valuable
+    // attacker or stopping when that's better. Pins are not considered.
+    pub fn see<S: SliderAttacks>(&self, lookup: &LookUp<S>, mv: Move) -> i32 {
+        if mv.is_castle() {
+            return 0;
+        }
+        let from = mv.from();
+        let to = mv.to();
+        let mut occupancy = self.occupancy() ^ from.bitboard();
+
+        // gain[d]: what the side making capture d is up, if the exchange ends there
+        let mut gain = [0i32; 32];
+        gain[0] = if mv.is_en_passant() {
+            occupancy ^= Square::from_index(to as usize ^ 8).bitboard();
+            value(PieceType::Pawn)
+        } else {
+            self.squares[to as usize].map_or(0, |piece| value(piece.r#type))
+        };
+        let mut on_square = value(self.squares[from as usize].unwrap().r#type);
+        if let Some(promotion) = mv.promotion() {
+            gain[0] += value(promotion) - value(PieceType::Pawn);
+            on_square = value(promotion);
+        }
+
+        let mut attackers = self.attackers_to(lookup, to, occupancy);
+        let mut side = self.active_color.opposite();
+        let mut depth = 0;
+        loop {
+            let ours = attackers & self.colors[side as usize];
+            let Some(piece) = ATTACKER_ORDER
+                .into_iter()
+                .find(|&piece| !(ours & self.pieces[piece as usize]).is_empty())
+            else {
+                break;
+            };
+            let sq = (ours & self.pieces[piece as usize]).lsb();
+            let occupancy_after = occupancy ^ sq.bitboard();
+            let attackers_after =
+                (attackers | self.slider_attackers(lookup, to, occupancy_after)) & occupancy_after;
+            // The king may only take last
+            if piece == PieceType::King
+                && !(attackers_after & self.colors[side.opposite() as usize]).is_empty()
+            {
+                break;
+            }
+
+            depth += 1;
+            gain[depth] = on_square - gain[depth - 1];
+            on_square = value(piece);
+            occupancy = occupancy_after;
+            attackers = attackers_after;
+            side = side.opposite();
+        }
+
+        // Each side may stop capturing whenever carrying on would cost it
+        while depth > 0 {
+            gain[depth - 1] = -(-gain[depth - 1]).max(gain[depth]);
+            depth -= 1;
+        }
+        gain[0]
+    }
+
+    pub fn see_ge<S: SliderAttacks>(&self, lookup: &LookUp<S>, mv: Move, threshold: i32) -> bool {
+        self.see(lookup, mv) >= threshold
+    }
+
+    // Bishops, rooks and queens of both colours that see sq through occupancy,
+    // picking up x-rays once the pieces in front have left
+    fn slider_attackers<S: SliderAttacks>(
+        &self,
+        lookup: &LookUp<S>,
+        sq: Square,
+        occupancy: Bitboard,
+    ) -> Bitboard {
+        let queens = self.pieces[PieceType::Queen as usize];
+        (lookup.bishop_moves(sq, occupancy) & (self.pieces[PieceType::Bishop as usize] | queens))
+            | (lookup.rook_moves(sq, occupancy) & (self.pieces[PieceType::Rook as usize] | queens))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The two examples from the chessprogramming wiki's SEE page, then the SEE test
+    // positions from the Carballo engine, which many engines check themselves
+    // against. Their values use the same piece values as SEE_VALUES. The move is
+    // in UCI notation.
+    const CASES: &[(&str, &str, &str, i32)] = &[
+        (
+            "undefended pawn",
+            "1k1r4/1pp4p/p7/4p3/8/P5P1/1PP4P/2K1R3 w - - 0 1",
+            "e1e5",
+            100,
+        ),
+        (
+            "x-rays on both sides",
+            "1k1r3q/1ppn3p/p4b2/4p3/8/P2N2P1/1PP1R1BP/2K1Q3 w - - 0 1",
+            "d3e5",
+            -200,
+        ),
+        (
+            "carballo 1",
+            "6k1/1pp4p/p1pb4/6q1/3P1pRr/2P4P/PP1Br1P1/5RKN w - - 0 1",
+            "f1f4",
+            -100,
+        ),
+        (
+            "carballo 2",
+            "5rk1/1pp2q1p/p1pb4/8/3P1NP1/2P5/1P1BQ1P1/5RK1 b - - 0 1",
+            "d6f4",
+            0,
+        ),
+        (
+            "carballo 3",
+            "4R3/2r3p1/5bk1/1p1r3p/p2PR1P1/P1BK1P2/1P6/8 b - - 0 1",
+            "h5g4",
+            0,
+        ),
+        (
+            "carballo 4",
+            "4R3/2r3p1/5bk1/1p1r1p1p/p2PR1P1/P1BK1P2/1P6/8 b - - 0 1",
+            "h5g4",
+            0,
+        ),
+        (
+            "carballo 5",
+            "4r1k1/5pp1/nbp4p/1p2p2q/1P2P1b1/1BP2N1P/1B2QPPK/3R4 b - - 0 1",
+            "g4f3",
+            0,
+        ),
+        (
+            "carballo 6",
+            "2r1r1k1/pp1bppbp/3p1np1/q3P3/2P2P2/1P2B3/P1N1B1PP/2RQ1RK1 b - - 0 1",
+            "d6e5",
+            100,
+        ),
+        (
+            "carballo 7",
+            "7r/5qpk/p1Qp1b1p/3r3n/BB3p2/5p2/P1P2P2/4RK1R w - - 0 1",
+            "e1e8",
+            0,
+        ),
+        (
+            "carballo 8",
+            "6rr/6pk/p1Qp1b1p/2n5/1B3p2/5p2/P1P2P2/4RK1R w - - 0 1",
+            "e1e8",
+            -500,
+        ),
+        (
+            "carballo 9",
+            "7r/5qpk/2Qp1b1p/1N1r3n/BB3p2/5p2/P1P2P2/4RK1R w - - 0 1",
+            "e1e8",
+            -500,
+        ),
+        (
+            "carballo 10",
+            "6RR/4bP2/8/8/5r2/3K4/5p2/4k3 w - - 0 1",
+            "f7f8q",
+            200,
+        ),
+        (
+            "carballo 11",
+            "6RR/4bP2/8/8/5r2/3K4/5p2/4k3 w - - 0 1",
+            "f7f8n",
+            200,
+        ),
+        (
+            "carballo 12",
+            "7R/5P2/8/8/6r1/3K4/5p2/4k3 w - - 0 1",
+            "f7f8q",
+            800,
+        ),
+        (
+            "carballo 13",
+            "7R/5P2/8/8/6r1/3K4/5p2/4k3 w - - 0 1",
+            "f7f8b",
+            200,
+        ),
+        (
+            "carballo 14",
+            "7R/4bP2/8/8/1q6/3K4/5p2/4k3 w - - 0 1",
+            "f7f8r",
+            -100,
+        ),
+        (
+            "carballo 15",
+            "8/4kp2/2npp3/1Nn5/1p2PQP1/7q/1PP1B3/4KR1r b - - 0 1",
+            "h1f1",
+            0,
+        ),
+        (
+            "carballo 16",
+            "8/4kp2/2npp3/1Nn5/1p2P1P1/7q/1PP1B3/4KR1r b - - 0 1",
+            "h1f1",
+            0,
+        ),
+        (
+            "carballo 17",
+            "2r2r1k/6bp/p7/2q2p1Q/3PpP2/1B6/P5PP/2RR3K b - - 0 1",
+            "c5c1",
+            100,
+        ),
+        (
+            "carballo 18",
+            "r2qk1nr/pp2ppbp/2b3p1/2p1p3/8/2N2N2/PPPP1PPP/R1BQR1K1 w kq - 0 1",
+            "f3e5",
+            100,
+        ),
+        (
+            "carballo 19",
+            "6r1/4kq2/b2p1p2/p1pPb3/p1P2B1Q/2P4P/2B1R1P1/6K1 w - - 0 1",
+            "f4e5",
+            0,
+        ),
+        (
+            "carballo 20",
+            "3q2nk/pb1r1p2/np6/3P2Pp/2p1P3/2R4B/PQ3P1P/3R2K1 w - h6 0 1",
+            "g5h6",
+            0,
+        ),
+        (
+            "carballo 21",
+            "3q2nk/pb1r1p2/np6/3P2Pp/2p1P3/2R1B2B/PQ3P1P/3R2K1 w - h6 0 1",
+            "g5h6",
+            100,
+        ),
+        (
+            "carballo 22",
+            "2r4r/1P4pk/p2p1b1p/7n/BB3p2/2R2p2/P1P2P2/4RK2 w - - 0 1",
+            "c3c8",
+            500,
+        ),
+        (
+            "carballo 23",
+            "2r5/1P4pk/p2p1b1p/5b1n/BB3p2/2R2p2/P1P2P2/4RK2 w - - 0 1",
+            "c3c8",
+            300,
+        ),
+        (
+            "carballo 24",
+            "2r4k/2r4p/p7/2b2p1b/4pP2/1BR5/P1R3PP/2Q4K w - - 0 1",
+            "c3c5",
+            300,
+        ),
+        (
+            "carballo 25",
+            "8/pp6/2pkp3/4bp2/2R3b1/2P5/PP4B1/1K6 w - - 0 1",
+            "g2c6",
+            -200,
+        ),
+        (
+            "carballo 26",
+            "3r3k/3r4/2n1n3/8/3p4/2PR4/1B1Q4/3R3K w - - 0 1",
+            "d3d4",
+            -100,
+        ),
+        (
+            "carballo 27",
+            "1k1r4/1ppn3p/p4b2/4n3/8/P2N2P1/1PP1R1BP/2K1Q3 w - - 0 1",
+            "d3e5",
+            100,
+        ),
+        (
+            "carballo 28",
+            "rnb2b1r/ppp2kpp/5n2/4P3/q2P3B/5R2/PPP2PPP/RN1QKB2 w Q - 0 1",
+            "h4f6",
+            100,
+        ),
+        (
+            "carballo 29",
+            "r2q1rk1/2p1bppp/p2p1n2/1p2P3/4P1b1/1nP1BN2/PP3PPP/RN1QR1K1 b - - 0 1",
+            "g4f3",
+            0,
+        ),
+        (
+            "carballo 30",
+            "r1bqkb1r/2pp1ppp/p1n5/1p2p3/3Pn3/1B3N2/PPP2PPP/RNBQ1RK1 b kq - 0 1",
+            "c6d4",
+            0,
+        ),
+        (
+            "carballo 31",
+            "r1bq1r2/pp1ppkbp/4N1p1/n3P1B1/8/2N5/PPP2PPP/R2QK2R w KQ - 0 1",
+            "e6g7",
+            0,
+        ),
+        (
+            "carballo 32",
+            "r1bq1r2/pp1ppkbp/4N1pB/n3P3/8/2N5/PPP2PPP/R2QK2R w KQ - 0 1",
+            "e6g7",
+            300,
+        ),
+        (
+            "carballo 33",
+            "rnq1k2r/1b3ppp/p2bpn2/1p1p4/3N4/1BN1P3/PPP2PPP/R1BQR1K1 b kq - 0 1",
+            "d6h2",
+            -200,
+        ),
+        (
+            "carballo 34",
+            "rn2k2r/1bq2ppp/p2bpn2/1p1p4/3N4/1BN1P3/PPP2PPP/R1BQR1K1 b kq - 0 1",
+            "d6h2",
+            100,
+        ),
+        (
+            "carballo 35",
+            "rnbq1rk1/pppp1ppp/4pn2/8/1bPP4/P1N5/1PQ1PPPP/R1B1KBNR b KQ - 0 1",
+            "b4c3",
+            0,
+        ),
+        (
+            "carballo 36",
+            "r4rk1/3nppbp/bq1p1np1/2pP4/8/2N2NPP/PP2PPB1/R1BQR1K1 b - - 0 1",
+            "b6b2",
+            -800,
+        ),
+        (
+            "carballo 37",
+            "r4rk1/1q1nppbp/b2p1np1/2pP4/8/2N2NPP/PP2PPB1/R1BQR1K1 b - - 0 1",
+            "f6d5",
+            -200,
+        ),
+        (
+            "carballo 38",
+            "1r3r2/5p2/4p2p/2k1n1P1/2PN1nP1/1P3P2/8/2KR1B1R b - - 0 1",
+            "b8b3",
+            -400,
+        ),
+        (
+            "carballo 39",
+            "1r3r2/5p2/4p2p/4n1P1/kPPN1nP1/5P2/8/2KR1B1R b - - 0 1",
+            "b8b4",
+            100,
+        ),
+        (
+            "carballo 40",
+            "2r2rk1/5pp1/pp5p/q2p4/P3n3/1Q3NP1/1P2PP1P/2RR2K1 b - - 0 1",
+            "c8c1",
+            0,
+        ),
+        (
+            "carballo 41",
+            "5rk1/5pp1/2r4p/5b2/2R5/6Q1/R1P1qPP1/5NK1 b - - 0 1",
+            "f5c2",
+            -100,
+        ),
+        (
+            "carballo 42",
+            "1r3r1k/p4pp1/2p1p2p/qpQP3P/2P5/3R4/PP3PP1/1K1R4 b - - 0 1",
+            "a5a2",
+            -800,
+        ),
+        (
+            "carballo 43",
+            "1r5k/p4pp1/2p1p2p/qpQP3P/2P2P2/1P1R4/P4rP1/1K1R4 b - - 0 1",
+            "a5a2",
+            100,
+        ),
+        (
+            "carballo 44",
+            "r2q1rk1/1b2bppp/p2p1n2/1ppNp3/3nP3/P2P1N1P/BPP2PP1/R1BQR1K1 w - - 0 1",
+            "d5e7",
+            0,
+        ),
+        (
+            "carballo 45",
+            "rnbqrbn1/pp3ppp/3p4/2p2k2/4p3/3B1K2/PPP2PPP/RNB1Q1NR w - - 0 1",
+            "d3e4",
+            100,
+        ),
+        (
+            "carballo 46",
+            "rnb1k2r/p3p1pp/1p3p1b/7n/1N2N3/3P1PB1/PPP1P1PP/R2QKB1R w KQkq - 0 1",
+            "e4d6",
+            -200,
+        ),
+        (
+            "carballo 47",
+            "r1b1k2r/p4npp/1pp2p1b/7n/1N2N3/3P1PB1/PPP1P1PP/R2QKB1R w KQkq - 0 1",
+            "e4d6",
+            0,
+        ),
+        (
+            "carballo 48",
+            "2r1k2r/pb4pp/5p1b/2KB3n/4N3/2NP1PB1/PPP1P1PP/R2Q3R w k - 0 1",
+            "d5c6",
+            -300,
+        ),
+        (
+            "carballo 49",
+            "2r1k2r/pb4pp/5p1b/2KB3n/1N2N3/3P1PB1/PPP1P1PP/R2Q3R w k - 0 1",
+            "d5c6",
+            0,
+        ),
+        (
+            "carballo 50",
+            "2r1k3/pbr3pp/5p1b/2KB3n/1N2N3/3P1PB1/PPP1P1PP/R2Q3R w - - 0 1",
+            "d5c6",
+            -300,
+        ),
+        (
+            "carballo 51",
+            "5k2/p2P2pp/8/1pb5/1Nn1P1n1/6Q1/PPP4P/R3K1NR w KQ - 0 1",
+            "d7d8q",
+            800,
+        ),
+        (
+            "carballo 52",
+            "r4k2/p2P2pp/8/1pb5/1Nn1P1n1/6Q1/PPP4P/R3K1NR w KQ - 0 1",
+            "d7d8q",
+            -100,
+        ),
+        (
+            "carballo 53",
+            "5k2/p2P2pp/1b6/1p6/1Nn1P1n1/8/PPP4P/R2QK1NR w KQ - 0 1",
+            "d7d8q",
+            200,
+        ),
+        (
+            "carballo 54",
+            "4kbnr/p1P1pppp/b7/4q3/7n/8/PP1PPPPP/RNBQKBNR w KQk - 0 1",
+            "c7c8q",
+            -100,
+        ),
+        (
+            "carballo 55",
+            "4kbnr/p1P1pppp/b7/4q3/7n/8/PPQPPPPP/RNB1KBNR w KQk - 0 1",
+            "c7c8q",
+            200,
+        ),
+        (
+            "carballo 56",
+            "4kbnr/p1P4p/b1q5/5pP1/4n3/5Q2/PP1PPP1P/RNB1KBNR w KQk f6 0 1",
+            "g5f6",
+            0,
+        ),
+        (
+            "carballo 57",
+            "4kbnr/p1P4p/b1q5/5pP1/4n2Q/8/PP1PPP1P/RNB1KBNR w KQk f6 0 1",
+            "g5f6",
+            0,
+        ),
+        (
+            "carballo 58",
+            "1n2kb1r/p1P4p/2qb4/5pP1/4n2Q/8/PP1PPP1P/RNB1KBNR w KQk - 0 1",
+            "c7b8q",
+            200,
+        ),
+        (
+            "carballo 59",
+            "rnbqk2r/pp3ppp/2p1pn2/3p4/3P4/N1P1BN2/PPB1PPPb/R2Q1RK1 w kq - 0 1",
+            "g1h2",
+            300,
+        ),
+        (
+            "carballo 60",
+            "3N4/2K5/2n5/1k6/8/8/8/8 b - - 0 1",
+            "c6d8",
+            0,
+        ),
+        (
+            "carballo 61",
+            "3n3r/2P5/8/1k6/8/8/3Q4/4K3 w - - 0 1",
+            "c7d8q",
+            700,
+        ),
+        (
+            "carballo 62",
+            "r2n3r/2P1P3/4N3/1k6/8/8/8/4K3 w - - 0 1",
+            "e6d8",
+            300,
+        ),
+        (
+            "carballo 63",
+            "8/8/8/1k6/6b1/4N3/2p3K1/3n4 w - - 0 1",
+            "e3d1",
+            0,
+        ),
+        (
+            "carballo 64",
+            "8/8/1k6/8/8/2N1N3/4p1K1/3n4 w - - 0 1",
+            "e3d1",
+            100,
+        ),
+        (
+            "carballo 65",
+            "r1bqk1nr/pppp1ppp/2n5/1B2p3/1b2P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 0 1",
+            "e1g1",
+            0,
+        ),
+    ];
+
+    #[test]
+    fn see_values() {
+        let lookup = LookUp::init();
+        let mut failures = Vec::new();
+        for &(name, fen, uci, expected) in CASES {
+            let board = Board::from_fen(fen).unwrap();
+            let mv = board.parse_uci_move(&lookup, uci).unwrap();
+            let value = board.see(&lookup, mv);
+            if value != expected {
+                failures.push(format!(
+                    "{}: see {} {} (expected {})",
+                    name, uci, value, expected
+                ));
+            }
+        }
+        assert!(failures.is_empty(), "{}", failures.join("\n"));
+    }
+}
@@ -0,0 +1,267 @@
+use crate::bitboards::{Bitboard, Square};
+use crate::magics::Magic;
+use crate::movegen::{ray_attacks, BISHOP_DIRECTIONS, ROOK_DIRECTIONS};
+
+// Bishop and rook attacks from a square given the occupancy, blockers included.
+// LookUp and everything built on it is generic over this, so backends can be
+// benchmarked against each other or swapped for a table-free one.
+pub trait SliderAttacks {
+    fn bishop_moves(&self, sq: Square, occupancy: Bitboard) -> Bitboard;
+    fn rook_moves(&self, sq: Square, occupancy: Bitboard) -> Bitboard;
+
+    #[inline(always)]
+    fn queen_moves(&self, sq: Square, occupancy: Bitboard) -> Bitboard {
+        self.bishop_moves(sq, occupancy) | self.rook_moves(sq, occupancy)
+    }
+}
+
+// Magics, or PEXT where the build has the pext feature and the CPU has BMI2.
+// What LookUp::init uses.
+#[derive(Debug, Clone, Copy)]
+pub struct Detected {
+    // Only ever true when the CPU has BMI2
+    pext: bool,
+}
+
+impl Default for Detected {
+    fn default() -> Detected {
+        Detected {
+            pext: pext_available(),
+        }
+    }
+}
+
+impl Detected {
+    // Returns whether PEXT is in use now
+    pub fn use_pext(&mut self, enabled: bool) -> bool {
+        self.pext = enabled && pext_available();
+        self.pext
+    }
+
+    pub fn uses_pext(&self) -> bool {
+        self.pext
+    }
+}
+
+impl SliderAttacks for Detected {
+    #[inline(always)]
+    fn bishop_moves(&self, sq: Square, occupancy: Bitboard) -> Bitboard {
+        #[cfg(all(feature = "pext", target_arch = "x86_64"))]
+        if self.pext {
+            // Safe, pext is only set when the CPU has BMI2
+            return unsafe { crate::pext::bishop_moves(sq, occupancy) };
+        }
+        Magic.bishop_moves(sq, occupancy)
+    }
+
+    #[inline(always)]
+    fn rook_moves(&self, sq: Square, occupancy: Bitboard) -> Bitboard {
+        #[cfg(all(feature = "pext", target_arch = "x86_64"))]
+        if self.pext {
+            // Safe, pext is only set when the CPU has BMI2
+            return unsafe { crate::pext::rook_moves(sq, occupancy) };
+        }
+        Magic.rook_moves(sq, occupancy)
+    }
+}
+
+#[cfg(all(feature = "pext", target_arch = "x86_64"))]
+fn pext_available() -> bool {
+    crate::pext::available()
+}
+
+#[cfg(not(all(feature = "pext", target_arch = "x86_64")))]
+fn pext_available() -> bool {
+    false
+}
+
+// The empty-board ray from every square in each direction, rooks' first
+const DIRECTIONS: [(i8, u64); 8] = [
+    ROOK_DIRECTIONS[0],
+    ROOK_DIRECTIONS[1],
+    ROOK_DIRECTIONS[2],
+    ROOK_DIRECTIONS[3],
+    BISHOP_DIRECTIONS[0],
+    BISHOP_DIRECTIONS[1],
+    BISHOP_DIRECTIONS[2],
+    BISHOP_DIRECTIONS[3],
+];
+static RAYS: [[Bitboard; 64]; 8] = build_rays();
+
+const fn build_rays() -> [[Bitboard; 64]; 8] {
+    let mut rays = [[Bitboard::EMPTY; 64]; 8];
+    let mut direction = 0;
+    while direction < 8 {
+        let mut sq = 0;
+        while sq < 64 {
+            rays[direction][sq] =
+                ray_attacks(Square::ALL[sq], Bitboard::EMPTY, &[DIRECTIONS[direction]]);
+            sq += 1;
+        }
+        direction += 1;
+    }
+    rays
+}
+
+// Each ray cut off behind its first blocker, found with a bit scan from the
+// square's end of the ray. 4 KB of rays.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Classical;
+
+impl Classical {
+    #[inline(always)]
+    fn ray_moves(sq: Square, occupancy: Bitboard, first: usize) -> Bitboard {
+        let mut attacks = Bitboard::EMPTY;
+        for direction in first..first + 4 {
+            let ray = RAYS[direction][sq as usize];
+            let blockers = ray & occupancy;
+            attacks |= ray;
+            if !blockers.is_empty() {
+                let blocker = if DIRECTIONS[direction].0 > 0 {
+                    blockers.lsb()
+                } else {
+                    blockers.msb()
+                };
+                attacks &= !RAYS[direction][blocker as usize];
+            }
+        }
+        attacks
+    }
+}
+
+impl SliderAttacks for Classical {
+    #[inline(always)]
+    fn bishop_moves(&self, sq: Square, occupancy: Bitboard) -> Bitboard {
+        Classical::ray_moves(sq, occupancy, 4)
+    }
+
+    #[inline(always)]
+    fn rook_moves(&self, sq: Square, occupancy: Bitboard) -> Bitboard {
+        Classical::ray_moves(sq, occupancy, 0)
+    }
+}
+
+// Rays flooded through the empty squares in three doubling steps per direction.
+// No tables at all.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct KoggeStone;
+
+#[inline(always)]
+fn shift(bits: u64, by: i8) -> u64 {
+    if by > 0 {
+        bits << by
+    } else {
+        bits >> -by
+    }
+}
+
+impl KoggeStone {
+    #[inline(always)]
+    fn fill(sq: Square, empty: u64, (step, wrap): (i8, u64)) -> u64 {
+        let mut fill = 1u64 << sq as u8;
+        // Squares a step may land on without having wrapped round the board
+        let mut open = empty & !wrap;
+        fill |= open & shift(fill, step);
+        open &= shift(open, step);
+        fill |= open & shift(fill, 2 * step);
+        open &= shift(open, 2 * step);
+        fill |= open & shift(fill, 4 * step);
+        // One more step onto the blocker
+        shift(fill, step) & !wrap
+    }
+
+    #[inline(always)]
+    fn moves(sq: Square, occupancy: Bitboard, directions: &[(i8, u64); 4]) -> Bitboard {
+        let empty = !occupancy.0;
+        Bitboard(
+            KoggeStone::fill(sq, empty, directions[0])
+                | KoggeStone::fill(sq, empty, directions[1])
+                | KoggeStone::fill(sq, empty, directions[2])
+                | KoggeStone::fill(sq, empty, directions[3]),
+        )
+    }
+}
+
+impl SliderAttacks for KoggeStone {
+    #[inline(always)]
+    fn bishop_moves(&self, sq: Square, occupancy: Bitboard) -> Bitboard {
+        KoggeStone::moves(sq, occupancy, &BISHOP_DIRECTIONS)
+    }
+
+    #[inline(always)]
+    fn rook_moves(&self, sq: Square, occupancy: Bitboard) -> Bitboard {
+        KoggeStone::moves(sq, occupancy, &ROOK_DIRECTIONS)
+    }
+}
+
+// The file, rank, diagonal and anti-diagonal through every square, without the
+// square itself
+const FILE: usize = 0;
+const RANK: usize = 1;
+const DIAGONAL: usize = 2;
+const ANTI_DIAGONAL: usize = 3;
+static LINES: [[u64; 64]; 4] = build_lines();
+
+const fn build_lines() -> [[u64; 64]; 4] {
+    let mut lines = [[0; 64]; 4];
+    let mut sq = 0;
+    while sq < 64 {
+        let square = Square::ALL[sq];
+        let (north, south, east, west) = (
+            ROOK_DIRECTIONS[0],
+            ROOK_DIRECTIONS[1],
+            ROOK_DIRECTIONS[2],
+            ROOK_DIRECTIONS[3],
+        );
+        let (north_east, north_west, south_east, south_west) = (
+            BISHOP_DIRECTIONS[0],
+            BISHOP_DIRECTIONS[1],
+            BISHOP_DIRECTIONS[2],
+            BISHOP_DIRECTIONS[3],
+        );
+        lines[FILE][sq] = ray_attacks(square, Bitboard::EMPTY, &[north, south]).0;
+        lines[RANK][sq] = ray_attacks(square, Bitboard::EMPTY, &[east, west]).0;
+        lines[DIAGONAL][sq] = ray_attacks(square, Bitboard::EMPTY, &[north_east, south_west]).0;
+        lines[ANTI_DIAGONAL][sq] =
+            ray_attacks(square, Bitboard::EMPTY, &[north_west, south_east]).0;
+        sq += 1;
+    }
+    lines
+}
+
+// o - 2r borrows up to the first blocker above the square, doing the same on the
+// reversed board finds the one below. 2 KB of lines.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Hyperbola;
+
+impl Hyperbola {
+    // reverse has to map the line onto itself backwards. Swapping bytes does that
+    // for anything with one square per rank, ranks need every bit reversed.
+    #[inline(always)]
+    fn line_moves(sq: Square, occupancy: Bitboard, line: usize, reverse: fn(u64) -> u64) -> u64 {
+        let mask = LINES[line][sq as usize];
+        let piece = 1u64 << sq as u8;
+        let blockers = occupancy.0 & mask;
+        let forward = blockers.wrapping_sub(piece.wrapping_mul(2));
+        let backward = reverse(reverse(blockers).wrapping_sub(reverse(piece).wrapping_mul(2)));
+        (forward ^ backward) & mask
+    }
+}
+
+impl SliderAttacks for Hyperbola {
+    #[inline(always)]
+    fn bishop_moves(&self, sq: Square, occupancy: Bitboard) -> Bitboard {
+        Bitboard(
+            Hyperbola::line_moves(sq, occupancy, DIAGONAL, u64::swap_bytes)
+                | Hyperbola::line_moves(sq, occupancy, ANTI_DIAGONAL, u64::swap_bytes),
+        )
+    }
+
+    #[inline(always)]
+    fn rook_moves(&self, sq: Square, occupancy: Bitboard) -> Bitboard {
+        Bitboard(
+            Hyperbola::line_moves(sq, occupancy, FILE, u64::swap_bytes)
+                | Hyperbola::line_moves(sq, occupancy, RANK, u64::reverse_bits),
+        )
+    }
+}
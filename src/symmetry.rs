@@ -0,0 +1,58 @@
+use crate::bitboards::{Board, CastlingRights, Piece, Square};
+
+impl Board {
+    // The same position with the colours swapped: every piece flipped to the other
+    // side of the board and handed to the other player, Black to move if White was
+    pub fn mirrored(&self) -> Board {
+        let mut board = self.remapped(Square::flip_vertical, |piece| Piece {
+            color: piece.color.opposite(),
+            ..piece
+        });
+        board.active_color = self.active_color.opposite();
+        // White's rights become Black's: the bits and rook files swap in pairs
+        let rights = self.castling_rights.bits();
+        board.castling_rights =
+            CastlingRights::from_bits_truncate((rights << 2 | rights >> 2) & 0xf);
+        let files = self.castling_rook_files;
+        board.castling_rook_files = [files[2], files[3], files[0], files[1]];
+        board.en_passant = self.en_passant.map(Square::flip_vertical);
+        board.key = board.compute_key();
+        board
+    }
+
+    // The position reflected a-file to h-file. Castling is not symmetric that way,
+    // so this is None while any castling right remains.
+    pub fn mirrored_horizontally(&self) -> Option<Board> {
+        if self.castling_rights != CastlingRights::NONE {
+            return None;
+        }
+        let mut board = self.remapped(Square::flip_horizontal, |piece| piece);
+        board.en_passant = self.en_passant.map(Square::flip_horizontal);
+        board.key = board.compute_key();
+        Some(board)
+    }
+
+    // Pieces moved by square_map and changed by piece_map, all other state copied
+    fn remapped(
+        &self,
+        square_map: impl Fn(Square) -> Square,
+        piece_map: impl Fn(Piece) -> Piece,
+    ) -> Board {
+        let mut board = Board {
+            squares: [None; 64],
+            pieces: Default::default(),
+            colors: Default::default(),
+            ..self.clone()
+        };
+        for sq in Square::ALL {
+            if let Some(piece) = self.squares[sq as usize] {
+                let piece = piece_map(piece);
+                let sq = square_map(sq);
+                board.squares[sq as usize] = Some(piece);
+                board.pieces[piece.r#type as usize] |= sq.bitboard();
+                board.colors[piece.color as usize] |= sq.bitboard();
+            }
+        }
+        board
+    }
+}
@@ -0,0 +1,213 @@
+use crate::bitboards::{Board, PieceType, Square};
+use crate::movegen::LookUp;
+use crate::moves::Move;
+use crate::sliders::SliderAttacks;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UciMoveError {
+    // Not two squares and an optional promotion letter
+    Malformed { uci: String },
+    // Well formed, but not a legal move in this position
+    Illegal { uci: String },
+}
+
+impl std::fmt::Display for UciMoveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            UciMoveError::Malformed { uci } => write!(f, "malformed move: {}", uci),
+            UciMoveError::Illegal { uci } => write!(f, "illegal move: {}", uci),
+        }
+    }
+}
+
+impl std::error::Error for UciMoveError {}
+
+impl Board {
+    // Reads a move in UCI long algebraic notation and finds it among the legal moves.
+    // Castling may be sent king-takes-rook (e1h1) or, outside Chess960, as the king's
+    // own step (e1g1). In Chess960 the latter could also be a plain king move, so
+    // only king-takes-rook castles there.
+    pub fn parse_uci_move<S: SliderAttacks>(
+        &self,
+        lookup: &LookUp<S>,
+        uci: &str,
+    ) -> Result<Move, UciMoveError> {
+        let malformed = || UciMoveError::Malformed {
+            uci: uci.to_string(),
+        };
+        if !uci.is_ascii() || !(4..=5).contains(&uci.len()) {
+            return Err(malformed());
+        }
+        let from: Square = uci[0..2].parse().map_err(|_| malformed())?;
+        let to: Square = uci[2..4].parse().map_err(|_| malformed())?;
+        let promotion = match uci[4..].to_ascii_lowercase().as_str() {
+            "" => None,
+            "n" => Some(PieceType::Knight),
+            "b" => Some(PieceType::Bishop),
+            "r" => Some(PieceType::Rook),
+            "q" => Some(PieceType::Queen),
+            _ => return Err(malformed()),
+        };
+
+        self.legal_moves(lookup)
+            .iter()
+            .find(|mv| {
+                let lands = mv.to() == to
+                    || (mv.is_castle() && !self.chess960 && mv.castling_king_to() == to);
+                mv.from() == from && lands && mv.promotion() == promotion
+            })
+            .copied()
+            .ok_or_else(|| UciMoveError::Illegal {
+                uci: uci.to_string(),
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::perft::SUITE;
+
+    fn uci_for(board: &Board, mv: Move) -> String {
+        if board.chess960() {
+            mv.to_uci_chess960()
+        } else {
+            mv.to_uci()
+        }
+    }
+
+    // Each expects the move written back in the board's notation, or the error message
+    const CASES: &[(&str, &str, &str, Result<&str, &str>)] = &[
+        (
+            "double push",
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "e2e4",
+            Ok("e2e4"),
+        ),
+        (
+            "upper case promotion",
+            "8/4P1k1/8/8/8/8/8/4K3 w - - 0 1",
+            "e7e8Q",
+            Ok("e7e8q"),
+        ),
+        (
+            "castling as the king's step",
+            "4k3/8/8/8/8/8/8/4K2R w K - 0 1",
+            "e1g1",
+            Ok("e1g1"),
+        ),
+        (
+            "castling as king takes rook",
+            "4k3/8/8/8/8/8/8/4K2R w K - 0 1",
+            "e1h1",
+            Ok("e1g1"),
+        ),
+        (
+            "chess960 castling",
+            "4k3/8/8/8/8/8/8/4K2R w H - 0 1",
+            "e1h1",
+            Ok("e1h1"),
+        ),
+        (
+            "pawn three squares",
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "e2e5",
+            Err("illegal move: e2e5"),
+        ),
+        (
+            "promotion off the last rank",
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "e2e4q",
+            Err("illegal move: e2e4q"),
+        ),
+        (
+            "promotion left out",
+            "8/4P1k1/8/8/8/8/8/4K3 w - - 0 1",
+            "e7e8",
+            Err("illegal move: e7e8"),
+        ),
+        (
+            "castling without the right",
+            "4k3/8/8/8/8/8/8/4K2R w - - 0 1",
+            "e1g1",
+            Err("illegal move: e1g1"),
+        ),
+        (
+            "castling through check",
+            "4kr2/8/8/8/8/8/8/4K2R w K - 0 1",
+            "e1g1",
+            Err("illegal move: e1g1"),
+        ),
+        (
+            "chess960 castling as the king's step",
+            "4k3/8/8/8/8/8/8/4K2R w H - 0 1",
+            "e1g1",
+            Err("illegal move: e1g1"),
+        ),
+        (
+            "square off the board",
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "e2e9",
+            Err("malformed move: e2e9"),
+        ),
+        (
+            "unknown promotion",
+            "8/4P1k1/8/8/8/8/8/4K3 w - - 0 1",
+            "e7e8k",
+            Err("malformed move: e7e8k"),
+        ),
+        (
+            "too short",
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "e2",
+            Err("malformed move: e2"),
+        ),
+    ];
+
+    #[test]
+    fn accepted_and_rejected_moves() {
+        let lookup = LookUp::init();
+        let mut failures = Vec::new();
+        for &(name, fen, uci, expected) in CASES {
+            let board = Board::from_fen(fen).unwrap();
+            let result = board
+                .parse_uci_move(&lookup, uci)
+                .map(|mv| uci_for(&board, mv))
+                .map_err(|e| e.to_string());
+            if result.as_deref().map_err(String::as_str) != expected {
+                failures.push(format!("{}: {:?} (expected {:?})", name, result, expected));
+            }
+        }
+        assert!(failures.is_empty(), "{}", failures.join("\n"));
+    }
+
+    // Every legal move within depth reads back, both as the board writes it and,
+    // outside Chess960, with castling as king takes rook
+    fn round_trip(board: &mut Board, lookup: &LookUp, depth: u32) {
+        if depth == 0 {
+            return;
+        }
+        for &mv in board.legal_moves(lookup).iter() {
+            for uci in [uci_for(board, mv), mv.to_uci_chess960()] {
+                assert_eq!(
+                    board.parse_uci_move(lookup, &uci),
+                    Ok(mv),
+                    "{} in {}",
+                    uci,
+                    board.to_fen()
+                );
+            }
+            let undo = board.make_move(mv);
+            round_trip(board, lookup, depth - 1);
+            board.unmake_move(mv, &undo);
+        }
+    }
+
+    #[test]
+    fn round_trip_over_perft_suite() {
+        let lookup = LookUp::init();
+        for case in SUITE {
+            round_trip(&mut Board::from_fen(case.fen).unwrap(), &lookup, 2);
+        }
+    }
+}
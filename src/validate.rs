@@ -0,0 +1,135 @@
+use crate::bitboards::{Bitboard, Board, CastlingRights, Color, Piece, PieceType, Square};
+
+const PIECE_TYPES: [PieceType; 6] = [
+    PieceType::Pawn,
+    PieceType::Bishop,
+    PieceType::Knight,
+    PieceType::Rook,
+    PieceType::Queen,
+    PieceType::King,
+];
+
+impl Board {
+    // Checks that squares, pieces and colors describe the same position and that the
+    // castling rights, en passant square and key fit it. Reports the first problem found.
+    pub fn validate(&self) -> Result<(), String> {
+        let mut occupied = Bitboard::EMPTY;
+        for piece_type in PIECE_TYPES {
+            let bb = self.pieces[piece_type as usize];
+            if !(occupied & bb).is_empty() {
+                return Err(format!(
+                    "{:?} bitboard overlaps another piece type",
+                    piece_type
+                ));
+            }
+            occupied |= bb;
+        }
+        if !(self.colors[0] & self.colors[1]).is_empty() {
+            return Err("White and Black bitboards overlap".to_string());
+        }
+        if occupied != self.colors[0] | self.colors[1] {
+            return Err("piece and colour bitboards cover different squares".to_string());
+        }
+        for sq in Square::ALL {
+            let found = self.piece_from_bitboards(sq);
+            if self.squares[sq as usize] != found {
+                return Err(format!(
+                    "{} holds {:?} but the bitboards say {:?}",
+                    sq, self.squares[sq as usize], found
+                ));
+            }
+        }
+
+        for color in [Color::White, Color::Black] {
+            let kings =
+                (self.pieces[PieceType::King as usize] & self.colors[color as usize]).popcount();
+            if kings != 1 {
+                return Err(format!("{:?} has {} kings", color, kings));
+            }
+        }
+        if self.opponent_in_check() {
+            return Err("the side not to move is in check".to_string());
+        }
+
+        for (idx, &file) in self.castling_rook_files.iter().enumerate() {
+            let right = CastlingRights::from_bits_truncate(1 << idx);
+            if !self.castling_rights.contains(right) {
+                continue;
+            }
+            let (color, rank) = if idx < 2 {
+                (Color::White, 0)
+            } else {
+                (Color::Black, 7)
+            };
+            let king = (self.pieces[PieceType::King as usize] & self.colors[color as usize]).lsb();
+            let rook = Square::from_coords(file, rank).unwrap();
+            let rook_side = if idx % 2 == 0 {
+                file > king.file()
+            } else {
+                file < king.file()
+            };
+            if king.rank() != rank
+                || !rook_side
+                || self.squares[rook as usize]
+                    != Some(Piece {
+                        r#type: PieceType::Rook,
+                        color,
+                    })
+            {
+                return Err(format!(
+                    "{:?} without the king and a rook on {}",
+                    right, rook
+                ));
+            }
+        }
+
+        if let Some(ep) = self.en_passant {
+            let rank = match self.active_color {
+                Color::White => 5,
+                Color::Black => 2,
+            };
+            if ep.rank() != rank || !self.en_passant_is_consistent(ep) {
+                return Err(format!("en passant square {} doesn't fit the position", ep));
+            }
+        }
+
+        if self.key != self.compute_key() {
+            return Err(format!(
+                "key {:016x} should be {:016x}",
+                self.key,
+                self.compute_key()
+            ));
+        }
+        Ok(())
+    }
+
+    fn piece_from_bitboards(&self, sq: Square) -> Option<Piece> {
+        let r#type = PIECE_TYPES
+            .into_iter()
+            .find(|&piece_type| self.pieces[piece_type as usize].contains(sq))?;
+        let color = if self.colors[Color::White as usize].contains(sq) {
+            Color::White
+        } else {
+            Color::Black
+        };
+        Some(Piece { r#type, color })
+    }
+
+    // An enemy pawn stands just past ep and both squares it crossed are empty,
+    // as they must be right after a double push. Assumes ep is on the right rank.
+    pub(crate) fn en_passant_is_consistent(&self, ep: Square) -> bool {
+        let them = self.active_color.opposite();
+        let victim = Square::from_index(ep as usize ^ 8);
+        let origin = match them {
+            Color::White => Square::from_index(ep as usize - 8),
+            Color::Black => Square::from_index(ep as usize + 8),
+        };
+        self.squares[victim as usize]
+            == Some(Piece {
+                r#type: PieceType::Pawn,
+                color: them,
+            })
+            && self.squares[ep as usize].is_none()
+            && self.squares[origin as usize].is_none()
+    }
+}
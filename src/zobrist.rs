@@ -0,0 +1,87 @@
+use std::sync::OnceLock;
+
+use rand::{Rng, SeedableRng};
+use rand_xoshiro::Xoshiro256PlusPlus;
+
+use crate::bitboards::{Board, CastlingRights, Color, Piece, Square};
+
+// Fixed so keys, and anything stored under them, are the same on every run
+const SEED: u64 = 0x61_62_64_6f_43_68_65_73;
+
+pub struct Zobrist {
+    pub pieces: [[[u64; 64]; 6]; 2],
+    pub black_to_move: u64,
+    pub castling: [u64; 16],
+    pub en_passant: [u64; 8],
+}
+
+impl Zobrist {
+    fn init() -> Zobrist {
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(SEED);
+        let mut pieces = [[[0u64; 64]; 6]; 2];
+        for color in pieces.iter_mut() {
+            for piece in color.iter_mut() {
+                for key in piece.iter_mut() {
+                    *key = rng.gen();
+                }
+            }
+        }
+        let black_to_move = rng.gen();
+        let mut castling = [0u64; 16];
+        for key in castling.iter_mut() {
+            *key = rng.gen();
+        }
+        let mut en_passant = [0u64; 8];
+        for key in en_passant.iter_mut() {
+            *key = rng.gen();
+        }
+
+        Zobrist {
+            pieces,
+            black_to_move,
+            castling,
+            en_passant,
+        }
+    }
+
+    pub fn get() -> &'static Zobrist {
+        static KEYS: OnceLock<Zobrist> = OnceLock::new();
+        KEYS.get_or_init(Zobrist::init)
+    }
+
+    #[inline(always)]
+    pub fn piece(&self, piece: Piece, sq: Square) -> u64 {
+        self.pieces[piece.color as usize][piece.r#type as usize][sq as usize]
+    }
+
+    #[inline(always)]
+    pub fn castling(&self, rights: CastlingRights) -> u64 {
+        self.castling[rights.bits() as usize]
+    }
+
+    #[inline(always)]
+    pub fn en_passant(&self, sq: Option<Square>) -> u64 {
+        match sq {
+            Some(sq) => self.en_passant[sq.file() as usize],
+            None => 0,
+        }
+    }
+}
+
+impl Board {
+    // Key computed from scratch, make_move keeps `key` equal to this. The en passant
+    // square only counts when it can be taken.
+    pub fn compute_key(&self) -> u64 {
+        let keys = Zobrist::get();
+        let mut key = 0;
+        for (idx, piece) in self.squares.iter().enumerate() {
+            if let Some(piece) = piece {
+                key ^= keys.pieces[piece.color as usize][piece.r#type as usize][idx];
+            }
+        }
+        if self.active_color == Color::Black {
+            key ^= keys.black_to_move;
+        }
+        key ^ keys.castling(self.castling_rights) ^ keys.en_passant(self.en_passant_target())
+    }
+}